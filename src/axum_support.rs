@@ -0,0 +1,64 @@
+//! Integration with the axum web framework, enabled by the `axum` feature.
+//!
+//! [RpcChannelError] can be returned from handlers directly, it responds with
+//! the same `application/problem+json` body as the `actix` integration. The
+//! [Surreal] extractor borrows a client from the [SurrealPool] that is stored
+//! in the application state:
+//! ```rust,ignore
+//! async fn get_user(Surreal(mut client): Surreal) -> Result<Json<Option<User>>, RpcChannelError> {
+//!   let user = client.query("select * from User limit 1").find_one().await?;
+//!
+//!   Ok(Json(user))
+//! }
+//!
+//! let app = Router::new()
+//!   .route("/user", get(get_user))
+//!   .with_state(SurrealPool::new(clients).with_connect(|| connect_and_signin()));
+//! ```
+
+use axum::extract::FromRef;
+use axum::extract::FromRequestParts;
+use axum::http::header;
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+
+use crate::pool::PooledClient;
+use crate::rpc::RpcChannelError;
+use crate::SurrealPool;
+
+impl IntoResponse for RpcChannelError {
+  fn into_response(self) -> Response {
+    let status =
+      StatusCode::from_u16(self.http_status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    (
+      status,
+      [(header::CONTENT_TYPE, "application/problem+json")],
+      self.problem_details().to_string(),
+    )
+      .into_response()
+  }
+}
+
+/// Extracts a client out of the [SurrealPool] found in the application state,
+/// the client returns to the pool once the handler is done with it. The
+/// request is rejected with the [RpcChannelError] of the pool if a closed
+/// client couldn't be replaced.
+pub struct Surreal(pub PooledClient);
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for Surreal
+where
+  SurrealPool: FromRef<S>,
+  S: Send + Sync,
+{
+  type Rejection = RpcChannelError;
+
+  async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    let pool = SurrealPool::from_ref(state);
+
+    Ok(Self(pool.get().await?))
+  }
+}
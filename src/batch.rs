@@ -0,0 +1,198 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::rpc::RpcChannelError;
+use crate::rpc::RpcResult;
+use crate::RecordId;
+use crate::SurrealClient;
+use crate::SurrealResponseData;
+
+/// A list of calls that are sent back-to-back over the socket without waiting
+/// for the response of the previous ones, obtained through
+/// [`SurrealClient::batch()`].
+///
+/// The calls are independent from each other: they don't run in a transaction
+/// and one of them failing doesn't prevent the others from running. Their
+/// responses are returned in the order the calls were added:
+/// ```rust,ignore
+/// let mut responses = client
+///   .batch()
+///   .query("select * from user where age > $age", json!({ "age": 18 }))
+///   .select("file")
+///   .create("file", &new_file)?
+///   .send()
+///   .await?;
+///
+/// let (users, files, created): (Vec<User>, Vec<File>, Option<File>) =
+///   (responses.take(0)?, responses.take(1)?, responses.take_one(2)?);
+/// ```
+pub struct Batch<'a> {
+  client: &'a mut SurrealClient,
+  calls: Vec<(String, Value)>,
+}
+
+impl<'a> Batch<'a> {
+  pub(crate) fn new(client: &'a mut SurrealClient) -> Self {
+    Self {
+      client,
+      calls: Vec::new(),
+    }
+  }
+
+  /// Add a query to the batch, see [`SurrealClient::send_query()`].
+  pub fn query<S: Into<String>>(mut self, query: S, params: Value) -> Self {
+    self.calls.push((query.into(), params));
+    self
+  }
+
+  /// Add the selection of every record of a table, or of a single record
+  /// when `target` is a record id.
+  pub fn select(self, target: &str) -> Self {
+    let (query, params) = match target.parse::<RecordId>() {
+      Ok(id) => (
+        "SELECT * FROM type::thing($table, $id)",
+        json!({ "table": id.table(), "id": id.id_param() }),
+      ),
+      Err(_) => (
+        "SELECT * FROM type::table($table)",
+        json!({ "table": target }),
+      ),
+    };
+
+    self.query(query, params)
+  }
+
+  /// Add the creation of a record in a table, or of a record with a specific
+  /// id when `target` is a record id. The `data` must serialize into an
+  /// object.
+  pub fn create<T: Serialize>(self, target: &str, data: T) -> RpcResult<Self> {
+    let data = serde_json::to_value(data)?;
+
+    if !data.is_object() {
+      return Err(RpcChannelError::InvalidBinding {
+        message: "create expects a value that serializes into an object".to_owned(),
+      });
+    }
+
+    let (query, params) = match target.parse::<RecordId>() {
+      Ok(id) => (
+        "CREATE type::thing($table, $id) CONTENT $data",
+        json!({ "table": id.table(), "id": id.id_param(), "data": data }),
+      ),
+      Err(_) => (
+        "CREATE type::table($table) CONTENT $data",
+        json!({ "table": target, "data": data }),
+      ),
+    };
+
+    Ok(self.query(query, params))
+  }
+
+  /// The amount of calls in the batch.
+  pub fn len(&self) -> usize {
+    self.calls.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.calls.is_empty()
+  }
+
+  /// Send every call of the batch before awaiting any of the responses.
+  ///
+  /// An error is returned if one of the calls couldn't be sent, otherwise the
+  /// errors are kept in the [BatchResponse] next to the call that caused them.
+  pub async fn send(self) -> RpcResult<BatchResponse> {
+    let mut pending = Vec::with_capacity(self.calls.len());
+
+    for (query, params) in self.calls {
+      pending.push(self.client.send_query(query, params).await?);
+    }
+
+    let responses = futures::future::join_all(pending).await;
+
+    Ok(BatchResponse {
+      responses: responses.into_iter().map(Some).collect(),
+    })
+  }
+}
+
+/// The responses to the calls of a [Batch], in the order the calls were added.
+#[derive(Debug)]
+pub struct BatchResponse {
+  responses: Vec<Option<RpcResult<SurrealResponseData>>>,
+}
+
+impl BatchResponse {
+  pub fn len(&self) -> usize {
+    self.responses.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.responses.is_empty()
+  }
+
+  /// Take the raw response to the `n`-th call, any statement of the call that
+  /// failed is turned into an error.
+  ///
+  /// # Panics
+  /// If there is no `n`-th call or if its response was already taken.
+  pub fn take_response(&mut self, n: usize) -> RpcResult<SurrealResponseData> {
+    let calls = self.responses.len();
+    let response = self
+      .responses
+      .get_mut(n)
+      .unwrap_or_else(|| panic!("the batch has no call {n}, its length is {calls}"))
+      .take()
+      .unwrap_or_else(|| panic!("the response to the call {n} of the batch was already taken"));
+
+    response.and_then(|response| {
+      response.check_statements()?;
+
+      Ok(response)
+    })
+  }
+
+  /// Take every [T] returned by the first statement of the `n`-th call.
+  ///
+  /// # Panics
+  /// If there is no `n`-th call or if its response was already taken.
+  pub fn take<T: DeserializeOwned>(&mut self, n: usize) -> RpcResult<Vec<T>> {
+    let response = self.take_response(n)?;
+    let values = response
+      .get_nth_query_result(0)
+      .map(|query_results| query_results.results().clone())
+      .unwrap_or_default();
+
+    let deser_result: Vec<T> = serde_json::from_value(Value::Array(values))?;
+
+    Ok(deser_result)
+  }
+
+  /// Take the **first** [T] returned by the first statement of the `n`-th
+  /// call.
+  ///
+  /// # Panics
+  /// If there is no `n`-th call or if its response was already taken.
+  pub fn take_one<T: DeserializeOwned>(&mut self, n: usize) -> RpcResult<Option<T>> {
+    let response = self.take_response(n)?;
+    let value = response
+      .get_nth_query_result(0)
+      .and_then(|query_results| query_results.results().first().cloned());
+
+    match value {
+      None => Ok(None),
+      Some(inner) => {
+        let deser_result = serde_json::from_value::<T>(inner)?;
+
+        Ok(Some(deser_result))
+      }
+    }
+  }
+
+  /// Get the responses that were not taken yet, in the order of the calls.
+  pub fn into_responses(self) -> Vec<RpcResult<SurrealResponseData>> {
+    self.responses.into_iter().flatten().collect()
+  }
+}
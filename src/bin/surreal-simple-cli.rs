@@ -0,0 +1,436 @@
+//! A small command-line client for SurrealDB built on [SurrealClient], enabled
+//! by the `cli` feature:
+//! ```sh
+//! cargo run --features cli --bin surreal-simple-cli -- --ns test --db test repl
+//! echo "select * from account;" | surreal-simple-cli --ns test --db test query
+//! ```
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::Parser;
+use clap::Subcommand;
+use clap::ValueEnum;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use serde_json::json;
+use serde_json::Value;
+use surreal_simple_client::schema;
+use surreal_simple_client::SurrealClient;
+use tokio::io::AsyncReadExt;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(
+  name = "surreal-simple-cli",
+  version,
+  about = "A command-line client for SurrealDB"
+)]
+struct Cli {
+  /// The RPC endpoint of the SurrealDB instance
+  #[arg(long, default_value = "ws://127.0.0.1:8000/rpc")]
+  url: String,
+
+  /// The user to sign in with, along with --pass
+  #[arg(long, short)]
+  user: Option<String>,
+
+  #[arg(long, short)]
+  pass: Option<String>,
+
+  /// The namespace to use, along with --db
+  #[arg(long)]
+  ns: Option<String>,
+
+  #[arg(long)]
+  db: Option<String>,
+
+  /// How the results of the queries are printed
+  #[arg(long, value_enum, default_value_t = Format::Json)]
+  format: Format,
+
+  #[command(subcommand)]
+  command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Run the queries typed in an interactive prompt, this is the default
+  Repl,
+
+  /// Run the queries read from a file, or from stdin. The exit code is not
+  /// zero if a statement failed
+  Query { file: Option<PathBuf> },
+
+  /// Write a SurrealQL dump of the database into a file, or to stdout
+  Export { file: Option<PathBuf> },
+
+  /// Import a SurrealQL dump from a file, or from stdin
+  Import { file: Option<PathBuf> },
+
+  /// Measure the round-trip time to the instance
+  Ping,
+
+  /// Print the version of the instance and every definition of the database,
+  /// including the fields, indexes and events of its tables
+  Info,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+  Json,
+  Table,
+}
+
+#[tokio::main]
+async fn main() {
+  let cli = Cli::parse();
+
+  if let Err(error) = run(cli).await {
+    eprintln!("error: {error}");
+    std::process::exit(1);
+  }
+}
+
+async fn run(cli: Cli) -> CliResult<()> {
+  let mut client = SurrealClient::new(&cli.url).await?;
+
+  if let (Some(user), Some(pass)) = (cli.user, cli.pass) {
+    client.signin(user, pass).await?;
+  }
+
+  if let (Some(ns), Some(db)) = (cli.ns, cli.db) {
+    client.use_namespace(ns, db).await?;
+  }
+
+  match cli.command.unwrap_or(Command::Repl) {
+    Command::Repl => repl(&mut client, cli.format).await,
+    Command::Query { file } => {
+      let query = read_input(file).await?;
+
+      match run_query(&mut client, query, cli.format).await? {
+        0 => Ok(()),
+        1 => Err("a statement failed".into()),
+        failed => Err(format!("{failed} statements failed").into()),
+      }
+    }
+    Command::Export { file } => {
+      match file {
+        Some(file) => client.export(tokio::fs::File::create(file).await?).await?,
+        None => client.export(tokio::io::stdout()).await?,
+      };
+
+      Ok(())
+    }
+    Command::Import { file } => {
+      let report = |progress: surreal_simple_client::dump::ImportProgress| {
+        eprintln!(
+          "imported {} statements, {} bytes read",
+          progress.statements_done, progress.bytes_read
+        )
+      };
+      let chunk_size = surreal_simple_client::dump::DEFAULT_IMPORT_CHUNK_SIZE;
+
+      match file {
+        Some(file) => {
+          let file = tokio::fs::File::open(file).await?;
+
+          client
+            .import_with_progress(file, chunk_size, report)
+            .await?
+        }
+        None => {
+          client
+            .import_with_progress(tokio::io::stdin(), chunk_size, report)
+            .await?
+        }
+      };
+
+      Ok(())
+    }
+    Command::Ping => {
+      let start = Instant::now();
+      client.send_message("ping", json!([])).await?.await?;
+
+      println!("pong in {:?}", start.elapsed());
+
+      Ok(())
+    }
+    Command::Info => {
+      match client.server_version() {
+        Some(version) => println!("SurrealDB {version}"),
+        None => println!("SurrealDB, unknown version"),
+      };
+
+      let definitions = schema::read_database_definitions(&mut client).await?;
+      let rows: Vec<Value> = definitions
+        .into_iter()
+        .map(|(key, statement)| {
+          json!({
+            "kind": format!("{:?}", key.kind).to_lowercase(),
+            "table": key.table,
+            "name": key.name,
+            "definition": statement,
+          })
+        })
+        .collect();
+
+      match cli.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&rows)?),
+        Format::Table => println!("{}", render_table(&rows)),
+      };
+
+      Ok(())
+    }
+  }
+}
+
+async fn read_input(file: Option<PathBuf>) -> CliResult<String> {
+  match file {
+    Some(file) => Ok(tokio::fs::read_to_string(file).await?),
+    None => {
+      let mut input = String::new();
+      tokio::io::stdin().read_to_string(&mut input).await?;
+
+      Ok(input)
+    }
+  }
+}
+
+/// Read the queries from the prompt, a query spans lines until one of them
+/// ends with a `;`. The history is kept in `~/.surreal_simple_cli_history`.
+async fn repl(client: &mut SurrealClient, format: Format) -> CliResult<()> {
+  let mut editor = DefaultEditor::new()?;
+  let history =
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".surreal_simple_cli_history"));
+
+  if let Some(history) = &history {
+    // there is no history on the first run
+    let _ = editor.load_history(history);
+  }
+
+  let mut query = String::new();
+
+  loop {
+    let prompt = if query.is_empty() { "> " } else { ". " };
+
+    match editor.readline(prompt) {
+      Ok(line) => {
+        if query.is_empty() && matches!(line.trim(), ".exit" | ".quit") {
+          break;
+        }
+
+        query.push_str(&line);
+        query.push('\n');
+
+        if line.trim_end().ends_with(';') {
+          editor.add_history_entry(query.trim())?;
+
+          // the failed statements were already printed
+          if let Err(error) = run_query(client, std::mem::take(&mut query), format).await {
+            eprintln!("error: {error}");
+          }
+        }
+      }
+      // ctrl-c drops the query being typed
+      Err(ReadlineError::Interrupted) => query.clear(),
+      Err(ReadlineError::Eof) => break,
+      Err(error) => return Err(error.into()),
+    };
+  }
+
+  if let Some(history) = &history {
+    editor.save_history(history)?;
+  }
+
+  Ok(())
+}
+
+/// Send the query and print the result of every statement, then return how
+/// many statements failed.
+async fn run_query(client: &mut SurrealClient, query: String, format: Format) -> CliResult<usize> {
+  let response = client.send_query(query, json!({})).await?.await?;
+  let mut n = 0;
+  let mut failed = 0;
+
+  while let Some(result) = response.get_nth_query_result(n) {
+    n += 1;
+
+    match result.error() {
+      Some(error) => {
+        failed += 1;
+        eprintln!("-- statement {n} failed in {}: {error}", result.time);
+      }
+      None => {
+        println!("-- statement {n} ({})", result.time);
+
+        match format {
+          Format::Json => println!("{}", serde_json::to_string_pretty(result.results())?),
+          Format::Table => println!("{}", render_table(result.results())),
+        };
+      }
+    };
+  }
+
+  Ok(failed)
+}
+
+/// Render the rows as a text table, with a column for every field found in the
+/// rows. Rows that are not objects are rendered in a single `value` column.
+fn render_table(rows: &[Value]) -> String {
+  let mut columns: Vec<String> = Vec::new();
+
+  for row in rows {
+    match row {
+      Value::Object(fields) => {
+        for name in fields.keys() {
+          if !columns.contains(name) {
+            columns.push(name.clone());
+          }
+        }
+      }
+      _ if !columns.iter().any(|column| column == "value") => columns.push("value".to_owned()),
+      _ => {}
+    };
+  }
+
+  let cells: Vec<Vec<String>> = rows
+    .iter()
+    .map(|row| {
+      columns
+        .iter()
+        .map(|column| {
+          let value = match row {
+            Value::Object(fields) => fields.get(column),
+            value if column == "value" => Some(value),
+            _ => None,
+          };
+
+          match value {
+            Some(Value::String(text)) => text.clone(),
+            Some(value) => value.to_string(),
+            None => String::new(),
+          }
+        })
+        .collect()
+    })
+    .collect();
+
+  let widths: Vec<usize> = columns
+    .iter()
+    .enumerate()
+    .map(|(i, column)| {
+      cells
+        .iter()
+        .map(|row| row[i].chars().count())
+        .chain([column.chars().count()])
+        .max()
+        .unwrap_or_default()
+    })
+    .collect();
+
+  let render_line = |values: &[String]| {
+    values
+      .iter()
+      .zip(&widths)
+      .map(|(value, width)| format!("{value:width$}"))
+      .collect::<Vec<_>>()
+      .join(" | ")
+  };
+
+  let separator = widths
+    .iter()
+    .map(|width| "-".repeat(*width))
+    .collect::<Vec<_>>()
+    .join("-+-");
+
+  let mut table = vec![render_line(&columns), separator];
+  table.extend(cells.iter().map(|row| render_line(row)));
+  table.push(format!("({} rows)", rows.len()));
+
+  table.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+  use surreal_simple_client::testing::FakeResponse;
+  use surreal_simple_client::testing::FakeServer;
+  use surreal_simple_client::SurrealClient;
+
+  use super::render_table;
+  use super::run_query;
+  use super::Format;
+
+  #[tokio::test]
+  async fn it_counts_the_failed_statements() -> super::CliResult<()> {
+    let server = FakeServer::builder()
+      .on_query(
+        "from missing",
+        FakeResponse::Result(json!([
+          { "time": "0ns", "status": "OK", "result": [] },
+          { "time": "0ns", "status": "ERR", "result": "The table `missing` does not exist" },
+          { "time": "0ns", "status": "ERR", "result": "The table `other` does not exist" },
+        ])),
+      )
+      .start()
+      .await?;
+    let mut client = SurrealClient::new(&server.url()).await?;
+
+    let query = "select * from account; select * from missing; select * from other;";
+    assert_eq!(
+      2,
+      run_query(&mut client, query.to_owned(), Format::Json).await?
+    );
+
+    let query = "select * from account;";
+    assert_eq!(
+      0,
+      run_query(&mut client, query.to_owned(), Format::Json).await?
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn it_renders_a_column_for_every_field() {
+    let rows = [
+      json!({ "name": "one", "tags": ["a", "b"] }),
+      json!({ "name": "two", "age": 2 }),
+    ];
+
+    let expected = [
+      "name | tags      | age",
+      "-----+-----------+----",
+      "one  | [\"a\",\"b\"] |    ",
+      "two  |           | 2  ",
+      "(2 rows)",
+    ];
+
+    assert_eq!(expected.join("\n"), render_table(&rows));
+  }
+
+  #[test]
+  fn it_renders_the_scalar_rows_in_a_value_column() {
+    let rows = [
+      json!({ "name": "one" }),
+      json!("two"),
+      json!(3),
+      json!(null),
+    ];
+
+    let expected = [
+      "name | value",
+      "-----+------",
+      "one  |      ",
+      "     | two  ",
+      "     | 3    ",
+      "     | null ",
+      "(4 rows)",
+    ];
+
+    assert_eq!(expected.join("\n"), render_table(&rows));
+  }
+}
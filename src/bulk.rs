@@ -0,0 +1,119 @@
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::rpc::RpcChannelError;
+use crate::rpc::RpcResult;
+use crate::SurrealClient;
+
+/// The maximum amount of chunks that are sent to the database without having
+/// received their response when using [`SurrealClient::insert_bulk()`].
+pub const DEFAULT_BULK_CONCURRENCY: usize = 4;
+
+/// The outcome of the insertion of a single chunk of records by
+/// [`SurrealClient::insert_bulk()`].
+#[derive(Debug)]
+pub struct BulkInsertChunk {
+  /// The position of the chunk in the supplied records, starting at 0.
+  pub chunk: usize,
+
+  /// The amount of records the chunk contained.
+  pub len: usize,
+
+  /// The ids of the records that were created, or the error that caused the
+  /// whole chunk to be rejected.
+  pub result: RpcResult<Vec<String>>,
+}
+
+impl BulkInsertChunk {
+  pub fn is_ok(&self) -> bool {
+    self.result.is_ok()
+  }
+}
+
+/// Insert the `records` into `table`, `chunk_size` records at a time and with
+/// at most `concurrency` chunks awaiting their response at once. Refer to
+/// [`SurrealClient::insert_bulk()`].
+pub(crate) async fn insert_bulk<T: Serialize, I: IntoIterator<Item = T>>(
+  client: &mut SurrealClient, table: &str, records: I, chunk_size: usize, concurrency: usize,
+) -> RpcResult<Vec<BulkInsertChunk>> {
+  if table.is_empty() || !table.chars().all(|c| c.is_alphanumeric() || c == '_') {
+    return Err(RpcChannelError::InvalidBinding {
+      message: format!("`{table}` is not a valid table name"),
+    });
+  }
+
+  let query = format!("INSERT INTO {table} $records");
+  let chunk_size = chunk_size.max(1);
+  let concurrency = concurrency.max(1);
+
+  let mut records = records.into_iter();
+  let mut chunk_index = 0;
+  let mut in_flight = FuturesUnordered::new();
+  let mut chunks = Vec::new();
+
+  loop {
+    while in_flight.len() < concurrency {
+      let chunk: Vec<T> = records.by_ref().take(chunk_size).collect();
+
+      if chunk.is_empty() {
+        break;
+      }
+
+      let len = chunk.len();
+      let index = chunk_index;
+      chunk_index += 1;
+
+      let response = match serde_json::to_value(chunk) {
+        Ok(values) => {
+          client
+            .send_query(query.clone(), json!({ "records": values }))
+            .await
+        }
+        Err(inner) => Err(inner.into()),
+      };
+
+      match response {
+        Ok(response) => in_flight.push(async move { (index, len, response.await) }),
+        Err(error) => chunks.push(BulkInsertChunk {
+          chunk: index,
+          len,
+          result: Err(error),
+        }),
+      };
+    }
+
+    match in_flight.next().await {
+      Some((chunk, len, response)) => chunks.push(BulkInsertChunk {
+        chunk,
+        len,
+        result: response.and_then(|response| {
+          response.check_statements()?;
+
+          Ok(
+            response
+              .get_nth_query_result(0)
+              .map(|query_results| created_ids(query_results.results()))
+              .unwrap_or_default(),
+          )
+        }),
+      }),
+      None => break,
+    };
+  }
+
+  chunks.sort_by_key(|chunk| chunk.chunk);
+
+  Ok(chunks)
+}
+
+fn created_ids(rows: &[Value]) -> Vec<String> {
+  rows
+    .iter()
+    .filter_map(|row| row.get("id"))
+    .filter_map(|id| id.as_str())
+    .map(str::to_owned)
+    .collect()
+}
@@ -0,0 +1,768 @@
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+use flate2::Compress;
+use flate2::Decompress;
+use flate2::FlushCompress;
+use flate2::FlushDecompress;
+use flate2::Status;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::io::ReadBuf;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::client::uri_mode;
+use tokio_tungstenite::tungstenite::error::UrlError;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::stream::Mode;
+
+/// The value of the `Sec-WebSocket-Extensions` header that offers the
+/// compression, the client compresses every message on its own so the
+/// server doesn't need to keep its context.
+pub(crate) const DEFLATE_OFFER: &str = "permessage-deflate; client_no_context_takeover";
+
+/// The bytes the compressed messages end with once flushed, they are left out
+/// of the frames and added back before decompressing.
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// The largest message that is decompressed, like the largest message the
+/// websocket accepts.
+const MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+/// The decompressed messages are handed to the websocket in frames of at most
+/// this size, like the frames of the messages that were sent uncompressed.
+const MAX_FRAME_SIZE: usize = 1 << 20;
+
+/// The amount of transformed bytes that can wait to be written to the socket
+/// before the writes wait for the socket.
+const WRITE_BUFFER_SIZE: usize = 64 << 10;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+
+/// Describes how the messages are compressed once the `permessage-deflate`
+/// websocket extension is negotiated, refer to
+/// [`SurrealClient::with_compression()`](crate::SurrealClient::with_compression).
+///
+/// The messages are compressed in both directions, the SurrealDB instance
+/// decides which of its messages it compresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+  /// The messages smaller than this amount of bytes are sent uncompressed,
+  /// compressing them costs more than it saves.
+  pub threshold: usize,
+
+  /// The compression level, from 0 for the fastest to 9 for the smallest
+  /// messages.
+  pub level: u32,
+}
+
+impl Default for Compression {
+  fn default() -> Self {
+    Self {
+      threshold: 1024,
+      level: 6,
+    }
+  }
+}
+
+impl Compression {
+  /// Compress the messages of at least `threshold` bytes with the default
+  /// level.
+  pub fn threshold(threshold: usize) -> Self {
+    Self {
+      threshold,
+      ..Self::default()
+    }
+  }
+
+  pub fn level(mut self, level: u32) -> Self {
+    self.level = level.min(9);
+    self
+  }
+}
+
+/// The amount of messages and bytes that were compressed since the
+/// connection was opened, refer to
+/// [`SurrealClient::compression_stats()`](crate::SurrealClient::compression_stats).
+///
+/// The bytes are the payloads of the messages, without the framing of the
+/// websocket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+  /// The messages that were sent compressed.
+  pub sent_messages: u64,
+  /// The size of the messages that were sent compressed, before they were
+  /// compressed.
+  pub sent_bytes: u64,
+  /// The size of the messages that were sent compressed, once compressed.
+  pub sent_compressed_bytes: u64,
+
+  /// The messages that were received compressed.
+  pub received_messages: u64,
+  /// The size of the messages that were received compressed, once
+  /// decompressed.
+  pub received_bytes: u64,
+  /// The size of the messages that were received compressed, as they were
+  /// received.
+  pub received_compressed_bytes: u64,
+}
+
+impl CompressionStats {
+  /// The bytes that didn't go through the socket thanks to the compression,
+  /// in both directions.
+  pub fn saved_bytes(&self) -> u64 {
+    (self.sent_bytes + self.received_bytes)
+      .saturating_sub(self.sent_compressed_bytes + self.received_compressed_bytes)
+  }
+}
+
+/// The counters behind the [CompressionStats] of a connection, shared by the
+/// client and the stream of its socket.
+#[derive(Debug, Default)]
+pub(crate) struct CompressionCounters {
+  negotiated: AtomicBool,
+  sent_messages: AtomicU64,
+  sent_bytes: AtomicU64,
+  sent_compressed_bytes: AtomicU64,
+  received_messages: AtomicU64,
+  received_bytes: AtomicU64,
+  received_compressed_bytes: AtomicU64,
+}
+
+impl CompressionCounters {
+  /// Whether the peer accepted the compression during the handshake.
+  pub(crate) fn is_negotiated(&self) -> bool {
+    self.negotiated.load(Ordering::Relaxed)
+  }
+
+  pub(crate) fn stats(&self) -> CompressionStats {
+    CompressionStats {
+      sent_messages: self.sent_messages.load(Ordering::Relaxed),
+      sent_bytes: self.sent_bytes.load(Ordering::Relaxed),
+      sent_compressed_bytes: self.sent_compressed_bytes.load(Ordering::Relaxed),
+      received_messages: self.received_messages.load(Ordering::Relaxed),
+      received_bytes: self.received_bytes.load(Ordering::Relaxed),
+      received_compressed_bytes: self.received_compressed_bytes.load(Ordering::Relaxed),
+    }
+  }
+}
+
+/// The side of the websocket a [DeflateStream] is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Role {
+  Client,
+  Server,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Negotiation {
+  /// The handshake response wasn't seen yet.
+  Pending,
+  /// `reset_inflater` tells whether the peer compresses every message on its
+  /// own, without the context of the previous messages.
+  Accepted {
+    reset_inflater: bool,
+  },
+  Declined,
+}
+
+/// The compressed message being received, while its fragments arrive.
+struct IncomingMessage {
+  opcode: u8,
+  mask: Option<[u8; 4]>,
+  payload: Vec<u8>,
+}
+
+/// The header of a websocket frame, along with the range of its payload in
+/// the buffer it was read from.
+struct Frame {
+  fin: bool,
+  rsv1: bool,
+  opcode: u8,
+  mask: Option<[u8; 4]>,
+  payload_start: usize,
+  end: usize,
+}
+
+/// Implements the `permessage-deflate` websocket extension (RFC 7692) under
+/// the websocket, which doesn't support it: the frames of the socket are
+/// rewritten on the fly so the websocket only sees uncompressed frames.
+///
+/// The bytes of the handshake go through untouched, and the frames are only
+/// rewritten once the handshake response accepted the extension.
+pub(crate) struct DeflateStream<S> {
+  inner: S,
+  role: Role,
+  compression: Compression,
+  counters: Arc<CompressionCounters>,
+  negotiation: Negotiation,
+
+  /// The bytes read from the socket that weren't transformed yet, and the
+  /// transformed bytes that weren't read yet.
+  read_raw: Vec<u8>,
+  read_ready: Vec<u8>,
+  read_position: usize,
+  read_handshake_done: bool,
+  incoming: Option<IncomingMessage>,
+  inflater: Decompress,
+
+  /// The bytes written to the stream that weren't transformed yet, and the
+  /// transformed bytes that weren't written to the socket yet.
+  write_raw: Vec<u8>,
+  write_ready: Vec<u8>,
+  write_handshake_done: bool,
+  deflater: Compress,
+}
+
+impl<S> DeflateStream<S> {
+  pub(crate) fn new(
+    inner: S, role: Role, compression: Compression, counters: Arc<CompressionCounters>,
+  ) -> Self {
+    Self {
+      inner,
+      role,
+      compression,
+      counters,
+      negotiation: Negotiation::Pending,
+      read_raw: Vec::new(),
+      read_ready: Vec::new(),
+      read_position: 0,
+      read_handshake_done: false,
+      incoming: None,
+      inflater: Decompress::new(false),
+      write_raw: Vec::new(),
+      write_ready: Vec::new(),
+      write_handshake_done: false,
+      deflater: Compress::new(flate2::Compression::new(compression.level.min(9)), false),
+    }
+  }
+
+  /// Move the bytes read from the socket to the bytes ready to be read, once
+  /// transformed.
+  fn process_read(&mut self) -> io::Result<()> {
+    if !self.read_handshake_done {
+      let Some(end) = header_end(&self.read_raw) else {
+        return Ok(());
+      };
+
+      // the client reads the response, the server reads the request
+      if self.role == Role::Client {
+        let header = String::from_utf8_lossy(&self.read_raw[..end]).to_lowercase();
+        self.negotiate(&header);
+      }
+
+      self.read_ready.extend(self.read_raw.drain(..end));
+      self.read_handshake_done = true;
+    }
+
+    match self.negotiation {
+      Negotiation::Pending => Ok(()),
+      Negotiation::Declined => {
+        self.read_ready.append(&mut self.read_raw);
+
+        Ok(())
+      }
+      Negotiation::Accepted { reset_inflater } => {
+        while let Some(frame) = parse_frame(&self.read_raw)? {
+          self.read_frame(&frame, reset_inflater)?;
+          self.read_raw.drain(..frame.end);
+        }
+
+        Ok(())
+      }
+    }
+  }
+
+  fn read_frame(&mut self, frame: &Frame, reset_inflater: bool) -> io::Result<()> {
+    let bytes = &self.read_raw[..frame.end];
+    let payload = &bytes[frame.payload_start..];
+    let is_data = frame.opcode == OPCODE_TEXT || frame.opcode == OPCODE_BINARY;
+
+    let message = match (&mut self.incoming, frame.opcode) {
+      (None, _) if is_data && frame.rsv1 => IncomingMessage {
+        opcode: frame.opcode,
+        mask: frame.mask,
+        payload: unmasked(payload, frame.mask),
+      },
+      (Some(incoming), OPCODE_CONTINUATION) => {
+        incoming
+          .payload
+          .extend_from_slice(&unmasked(payload, frame.mask));
+
+        match frame.fin {
+          true => self.incoming.take().expect("the message is being received"),
+          false => return Ok(()),
+        }
+      }
+      // the control frames and the uncompressed messages are left as they are
+      _ => {
+        self.read_ready.extend_from_slice(bytes);
+
+        return Ok(());
+      }
+    };
+
+    if !frame.fin {
+      self.incoming = Some(message);
+
+      return Ok(());
+    }
+
+    if message.payload.len() > MAX_MESSAGE_SIZE {
+      return Err(invalid_data("the compressed message is too large"));
+    }
+
+    let mut compressed = message.payload;
+    compressed.extend_from_slice(&DEFLATE_TAIL);
+
+    let decompressed = inflate(&mut self.inflater, &compressed)?;
+
+    if reset_inflater {
+      self.inflater.reset(false);
+    }
+
+    self
+      .counters
+      .received_messages
+      .fetch_add(1, Ordering::Relaxed);
+    self
+      .counters
+      .received_bytes
+      .fetch_add(decompressed.len() as u64, Ordering::Relaxed);
+    self.counters.received_compressed_bytes.fetch_add(
+      (compressed.len() - DEFLATE_TAIL.len()) as u64,
+      Ordering::Relaxed,
+    );
+
+    let mut chunks = decompressed.chunks(MAX_FRAME_SIZE).peekable();
+    let mut opcode = message.opcode;
+
+    if chunks.peek().is_none() {
+      write_frame(&mut self.read_ready, true, false, opcode, message.mask, &[]);
+    }
+
+    while let Some(chunk) = chunks.next() {
+      let fin = chunks.peek().is_none();
+
+      write_frame(
+        &mut self.read_ready,
+        fin,
+        false,
+        opcode,
+        message.mask,
+        chunk,
+      );
+      opcode = OPCODE_CONTINUATION;
+    }
+
+    Ok(())
+  }
+
+  /// Move the bytes written to the stream to the bytes ready to be written to
+  /// the socket, once transformed.
+  fn process_write(&mut self) -> io::Result<()> {
+    if !self.write_handshake_done {
+      let Some(end) = header_end(&self.write_raw) else {
+        return Ok(());
+      };
+
+      // the server writes the response, the client writes the request
+      if self.role == Role::Server {
+        let header = String::from_utf8_lossy(&self.write_raw[..end]).to_lowercase();
+        self.negotiate(&header);
+      }
+
+      self.write_ready.extend(self.write_raw.drain(..end));
+      self.write_handshake_done = true;
+    }
+
+    match self.negotiation {
+      Negotiation::Pending => Ok(()),
+      Negotiation::Declined => {
+        self.write_ready.append(&mut self.write_raw);
+
+        Ok(())
+      }
+      Negotiation::Accepted { .. } => {
+        while let Some(frame) = parse_frame(&self.write_raw)? {
+          self.write_frame(&frame)?;
+          self.write_raw.drain(..frame.end);
+        }
+
+        Ok(())
+      }
+    }
+  }
+
+  /// Compress the unfragmented messages of at least the threshold, the other
+  /// frames are left as they are.
+  fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+    let bytes = &self.write_raw[..frame.end];
+    let payload_size = frame.end - frame.payload_start;
+    let is_data = frame.opcode == OPCODE_TEXT || frame.opcode == OPCODE_BINARY;
+
+    if !is_data || !frame.fin || frame.rsv1 || payload_size < self.compression.threshold {
+      self.write_ready.extend_from_slice(bytes);
+
+      return Ok(());
+    }
+
+    let payload = unmasked(&bytes[frame.payload_start..], frame.mask);
+    let mut compressed = deflate(&mut self.deflater, &payload)?;
+
+    if compressed.ends_with(&DEFLATE_TAIL) {
+      compressed.truncate(compressed.len() - DEFLATE_TAIL.len());
+    }
+
+    // the messages that don't shrink are sent as they are
+    if compressed.len() >= payload.len() {
+      self.write_ready.extend_from_slice(bytes);
+
+      return Ok(());
+    }
+
+    self.counters.sent_messages.fetch_add(1, Ordering::Relaxed);
+    self
+      .counters
+      .sent_bytes
+      .fetch_add(payload.len() as u64, Ordering::Relaxed);
+    self
+      .counters
+      .sent_compressed_bytes
+      .fetch_add(compressed.len() as u64, Ordering::Relaxed);
+
+    write_frame(
+      &mut self.write_ready,
+      true,
+      true,
+      frame.opcode,
+      frame.mask,
+      &compressed,
+    );
+
+    Ok(())
+  }
+
+  /// Read whether the extension was accepted from the lowercase `header` of
+  /// the handshake response.
+  fn negotiate(&mut self, header: &str) {
+    let extensions = header
+      .lines()
+      .filter_map(|line| line.split_once(':'))
+      .filter(|(name, _)| name.trim() == "sec-websocket-extensions")
+      .map(|(_, value)| value.trim())
+      .find(|value| value.starts_with("permessage-deflate"));
+
+    // the parameter of the peer that compresses the messages this side reads
+    let no_context_takeover = match self.role {
+      Role::Client => "server_no_context_takeover",
+      Role::Server => "client_no_context_takeover",
+    };
+
+    self.negotiation = match extensions {
+      Some(extension) => Negotiation::Accepted {
+        reset_inflater: extension.contains(no_context_takeover),
+      },
+      None => Negotiation::Declined,
+    };
+    self.counters.negotiated.store(
+      matches!(self.negotiation, Negotiation::Accepted { .. }),
+      Ordering::Relaxed,
+    );
+  }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> DeflateStream<S> {
+  /// Write the transformed bytes to the socket until it is full.
+  fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    while !self.write_ready.is_empty() {
+      match Pin::new(&mut self.inner).poll_write(cx, &self.write_ready)? {
+        Poll::Ready(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+        Poll::Ready(written) => {
+          self.write_ready.drain(..written);
+        }
+        Poll::Pending => return Poll::Pending,
+      };
+    }
+
+    Poll::Ready(Ok(()))
+  }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for DeflateStream<S> {
+  fn poll_read(
+    self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>,
+  ) -> Poll<io::Result<()>> {
+    let this = self.get_mut();
+
+    loop {
+      // the negotiation may have been settled by a write since the last read
+      this.process_read()?;
+
+      if this.read_position < this.read_ready.len() {
+        let ready = &this.read_ready[this.read_position..];
+        let size = ready.len().min(buf.remaining());
+
+        buf.put_slice(&ready[..size]);
+        this.read_position += size;
+
+        if this.read_position == this.read_ready.len() {
+          this.read_ready.clear();
+          this.read_position = 0;
+        }
+
+        return Poll::Ready(Ok(()));
+      }
+
+      let mut chunk = [0_u8; 8192];
+      let mut chunk_buf = ReadBuf::new(&mut chunk);
+
+      match Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf)? {
+        Poll::Pending => return Poll::Pending,
+        // the end of the stream
+        Poll::Ready(()) if chunk_buf.filled().is_empty() => return Poll::Ready(Ok(())),
+        Poll::Ready(()) => this.read_raw.extend_from_slice(chunk_buf.filled()),
+      };
+    }
+  }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for DeflateStream<S> {
+  fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+    let this = self.get_mut();
+
+    if this.poll_write_ready(cx)?.is_pending() && this.write_ready.len() >= WRITE_BUFFER_SIZE {
+      return Poll::Pending;
+    }
+
+    this.write_raw.extend_from_slice(buf);
+    this.process_write()?;
+
+    // the handshake is written without being flushed
+    if let Poll::Ready(Err(error)) = this.poll_write_ready(cx) {
+      return Poll::Ready(Err(error));
+    }
+
+    Poll::Ready(Ok(buf.len()))
+  }
+
+  fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    let this = self.get_mut();
+
+    // the negotiation may have been settled by a read since the last write
+    this.process_write()?;
+
+    match this.poll_write_ready(cx)? {
+      Poll::Ready(()) => Pin::new(&mut this.inner).poll_flush(cx),
+      Poll::Pending => Poll::Pending,
+    }
+  }
+
+  fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    let this = self.get_mut();
+
+    match this.poll_write_ready(cx)? {
+      Poll::Ready(()) => Pin::new(&mut this.inner).poll_shutdown(cx),
+      Poll::Pending => Poll::Pending,
+    }
+  }
+}
+
+/// Open the socket of the websocket `request` under a [DeflateStream]. The
+/// frames are rewritten on the socket itself, which leaves no room for TLS so
+/// only the `ws` urls are supported.
+pub(crate) async fn connect(
+  request: &Request, compression: Compression, counters: Arc<CompressionCounters>,
+) -> Result<DeflateStream<TcpStream>, tungstenite::Error> {
+  if let Mode::Tls = uri_mode(request.uri())? {
+    return Err(tungstenite::Error::Url(UrlError::TlsFeatureNotEnabled));
+  }
+
+  let host = request
+    .uri()
+    .host()
+    .ok_or(tungstenite::Error::Url(UrlError::NoHostName))?;
+  let port = request.uri().port_u16().unwrap_or(80);
+  let stream = TcpStream::connect((host, port)).await?;
+
+  Ok(DeflateStream::new(
+    stream,
+    Role::Client,
+    compression,
+    counters,
+  ))
+}
+
+/// The size of the HTTP header at the start of `bytes`, including the empty
+/// line that ends it, or [None] if it wasn't received in full yet.
+fn header_end(bytes: &[u8]) -> Option<usize> {
+  bytes
+    .windows(4)
+    .position(|window| window == b"\r\n\r\n")
+    .map(|position| position + 4)
+}
+
+/// Parse the frame at the start of `bytes`, or return [None] if it wasn't
+/// received in full yet.
+fn parse_frame(bytes: &[u8]) -> io::Result<Option<Frame>> {
+  let [first, second, ..] = *bytes else {
+    return Ok(None);
+  };
+
+  let (length, mut offset) = match second & 0x7f {
+    126 if bytes.len() >= 4 => (u64::from(u16::from_be_bytes([bytes[2], bytes[3]])), 4),
+    127 if bytes.len() >= 10 => (
+      u64::from_be_bytes(bytes[2..10].try_into().expect("the slice has 8 bytes")),
+      10,
+    ),
+    126 | 127 => return Ok(None),
+    length => (u64::from(length), 2),
+  };
+
+  let mask = match second & 0x80 != 0 {
+    true if bytes.len() >= offset + 4 => {
+      let mask = bytes[offset..offset + 4].try_into().ok();
+      offset += 4;
+      mask
+    }
+    true => return Ok(None),
+    false => None,
+  };
+
+  let length = usize::try_from(length)
+    .ok()
+    .filter(|length| *length <= MAX_MESSAGE_SIZE)
+    .ok_or_else(|| invalid_data("the frame is too large"))?;
+
+  if bytes.len() < offset + length {
+    return Ok(None);
+  }
+
+  Ok(Some(Frame {
+    fin: first & 0x80 != 0,
+    rsv1: first & 0x40 != 0,
+    opcode: first & 0x0f,
+    mask,
+    payload_start: offset,
+    end: offset + length,
+  }))
+}
+
+/// Append a frame holding the `payload` to `output`, the payload is masked
+/// with `mask` if there is one.
+fn write_frame(
+  output: &mut Vec<u8>, fin: bool, rsv1: bool, opcode: u8, mask: Option<[u8; 4]>, payload: &[u8],
+) {
+  output.push((u8::from(fin) << 7) | (u8::from(rsv1) << 6) | opcode);
+
+  let mask_bit = u8::from(mask.is_some()) << 7;
+
+  match payload.len() {
+    length @ 0..=125 => output.push(mask_bit | length as u8),
+    length @ 126..=0xffff => {
+      output.push(mask_bit | 126);
+      output.extend_from_slice(&(length as u16).to_be_bytes());
+    }
+    length => {
+      output.push(mask_bit | 127);
+      output.extend_from_slice(&(length as u64).to_be_bytes());
+    }
+  };
+
+  match mask {
+    Some(mask) => {
+      output.extend_from_slice(&mask);
+      output.extend(unmasked(payload, Some(mask)));
+    }
+    None => output.extend_from_slice(payload),
+  };
+}
+
+/// Apply the `mask` to the `payload`, which masks it or unmasks it.
+fn unmasked(payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+  match mask {
+    Some(mask) => payload
+      .iter()
+      .enumerate()
+      .map(|(i, byte)| byte ^ mask[i % 4])
+      .collect(),
+    None => payload.to_vec(),
+  }
+}
+
+/// Compress a whole message, every message is compressed without the context
+/// of the previous ones.
+fn deflate(deflater: &mut Compress, input: &[u8]) -> io::Result<Vec<u8>> {
+  deflater.reset();
+
+  let mut output = Vec::with_capacity(input.len() / 2 + 64);
+
+  loop {
+    let consumed = deflater.total_in() as usize;
+    output.reserve(1024);
+
+    deflater
+      .compress_vec(&input[consumed..], &mut output, FlushCompress::Sync)
+      .map_err(|error| invalid_data(&error.to_string()))?;
+
+    // the flush is complete once the output wasn't filled, the totals start
+    // over with the reset
+    if deflater.total_in() as usize == input.len() && output.len() < output.capacity() {
+      return Ok(output);
+    }
+  }
+}
+
+/// Decompress a whole message, with the context of the previous ones unless
+/// the inflater is reset between the messages.
+fn inflate(inflater: &mut Decompress, input: &[u8]) -> io::Result<Vec<u8>> {
+  let start = inflater.total_in();
+  let mut output = Vec::with_capacity(input.len() * 4);
+
+  loop {
+    let (consumed, produced) = (inflater.total_in(), inflater.total_out());
+    output.reserve(1024);
+
+    let status = inflater
+      .decompress_vec(
+        &input[(consumed - start) as usize..],
+        &mut output,
+        FlushDecompress::Sync,
+      )
+      .map_err(|error| invalid_data(&error.to_string()))?;
+
+    if output.len() > MAX_MESSAGE_SIZE {
+      return Err(invalid_data("the decompressed message is too large"));
+    }
+
+    if status == Status::StreamEnd {
+      // the peer ended its deflate stream, the next message starts a new one
+      inflater.reset(false);
+
+      return Ok(output);
+    }
+
+    let is_consumed = (inflater.total_in() - start) as usize == input.len();
+
+    // the message is complete once the output wasn't filled
+    if is_consumed && output.len() < output.capacity() {
+      return Ok(output);
+    }
+
+    if (inflater.total_in(), inflater.total_out()) == (consumed, produced) {
+      return Err(invalid_data("the compressed message is truncated"));
+    }
+  }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+
+use futures::SinkExt;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::errors::SurrealError;
+use crate::flow::InFlightPermit;
+use crate::flow::OUTGOING_QUEUE_CAPACITY;
+use crate::protocol;
+use crate::rpc::RpcChannelError;
+use crate::rpc::RpcResult;
+use crate::SurrealResponseData;
+
+pub(crate) type SurrealResponseSender = oneshot::Sender<RpcResult<SurrealResponseData>>;
+
+/// A frame waiting in the outgoing queue to be written to the socket, along
+/// with the id of its request.
+pub(crate) type OutgoingFrame = (String, Message);
+
+/// A request that awaits its response, along with the permit it holds until
+/// the response is received, refer to [FlowControl](crate::FlowControl).
+pub(crate) struct PendingRequest {
+  pub(crate) sender: SurrealResponseSender,
+  pub(crate) _permit: InFlightPermit,
+}
+
+/// A message as it is received from the SurrealDB instance, which is either
+/// the response to a request or the error it caused.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SurrealIncomingMessage {
+  Response(SurrealResponseData),
+  Error(SurrealError),
+}
+
+/// The state of a connection, shared by the client and the two tasks that
+/// own the halves of its socket.
+pub(crate) struct ConnectionState {
+  /// The requests that await their response, the map is [None] once the
+  /// connection is closed.
+  pending: Mutex<Option<HashMap<String, PendingRequest>>>,
+  unmatched_responses: AtomicUsize,
+}
+
+impl ConnectionState {
+  fn new() -> Self {
+    Self {
+      pending: Mutex::new(Some(HashMap::new())),
+      unmatched_responses: AtomicUsize::new(0),
+    }
+  }
+
+  fn lock(&self) -> MutexGuard<'_, Option<HashMap<String, PendingRequest>>> {
+    self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// Register a request before it is written to the socket, so its response
+  /// can't arrive before the request is known.
+  pub(crate) fn register(&self, id: String, request: PendingRequest) -> RpcResult<()> {
+    match self.lock().as_mut() {
+      Some(pending) => {
+        pending.insert(id, request);
+
+        Ok(())
+      }
+      None => Err(RpcChannelError::ClientClosed),
+    }
+  }
+
+  /// Remove the request `id` and send it the `result`.
+  pub(crate) fn resolve(&self, id: &str, result: RpcResult<SurrealResponseData>) -> bool {
+    let request = self.lock().as_mut().and_then(|pending| pending.remove(id));
+
+    match request {
+      Some(request) => {
+        // an error means the receiver is no longer listening, which is a
+        // possible & valid state.
+        let _ = request.sender.send(result);
+
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Close the connection, the pending requests fail with a
+  /// [`RpcChannelError::ClientClosed`] rather than hang.
+  pub(crate) fn close(&self) {
+    let pending = self.lock().take().unwrap_or_default();
+
+    for (_, request) in pending {
+      let _ = request.sender.send(Err(RpcChannelError::ClientClosed));
+    }
+  }
+
+  pub(crate) fn is_closed(&self) -> bool {
+    self.lock().is_none()
+  }
+
+  pub(crate) fn in_flight(&self) -> usize {
+    self.lock().as_ref().map(HashMap::len).unwrap_or_default()
+  }
+
+  pub(crate) fn unmatched_responses(&self) -> usize {
+    self.unmatched_responses.load(Ordering::Relaxed)
+  }
+}
+
+/// Spawn the task that writes the queued frames to the socket and the task
+/// that reads the responses from it. They run separately so the responses
+/// keep being read while a write waits for the server.
+///
+/// The tasks stop once the connection is closed, or once the returned queue
+/// is dropped along with the client.
+pub(crate) fn spawn<S>(
+  socket: WebSocketStream<S>,
+) -> (mpsc::Sender<OutgoingFrame>, Arc<ConnectionState>)
+where
+  S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+  let (mut socket_sink, mut socket_stream) = socket.split();
+  let (outgoing, mut outgoing_queue) = mpsc::channel::<OutgoingFrame>(OUTGOING_QUEUE_CAPACITY);
+  let state = Arc::new(ConnectionState::new());
+
+  let reader_state = state.clone();
+  let reader = tokio::spawn(async move {
+    while let Some(Ok(frame)) = socket_stream.next().await {
+      // the control frames are handled by the socket itself
+      if frame.is_ping() || frame.is_pong() || frame.is_close() {
+        continue;
+      }
+
+      let resolved = match protocol::decode_frame(frame).and_then(parse_incoming_message) {
+        Some((id, response)) => reader_state.resolve(&id, response),
+        None => false,
+      };
+
+      if !resolved {
+        reader_state
+          .unmatched_responses
+          .fetch_add(1, Ordering::Relaxed);
+      }
+    }
+
+    reader_state.close();
+  });
+
+  let writer_state = state.clone();
+  tokio::spawn(async move {
+    while let Some((id, frame)) = outgoing_queue.recv().await {
+      if let Err(inner) = socket_sink.send(frame).await {
+        writer_state.resolve(&id, Err(inner.into()));
+        break;
+      }
+    }
+
+    // the client was dropped or the socket failed
+    writer_state.close();
+    let _ = socket_sink.close().await;
+    reader.abort();
+  });
+
+  (outgoing, state)
+}
+
+/// Parse a message received from the SurrealDB instance and return the id of
+/// the request it answers along with the result of that request.
+///
+/// A message that couldn't be parsed is still routed to the request it answers
+/// as a [`RpcChannelError::SurrealBodyParsingError`] as long as its `id` can
+/// be read. [None] is returned otherwise.
+fn parse_incoming_message(value: Value) -> Option<(String, RpcResult<SurrealResponseData>)> {
+  let id = value.get("id")?.as_str()?.to_owned();
+
+  let result = match serde_json::from_value::<SurrealIncomingMessage>(value) {
+    Ok(SurrealIncomingMessage::Response(response)) => Ok(response),
+    Ok(SurrealIncomingMessage::Error(inner)) => Err(RpcChannelError::SurrealQueryError { inner }),
+    Err(inner) => Err(RpcChannelError::SurrealBodyParsingError { inner }),
+  };
+
+  Some((id, result))
+}
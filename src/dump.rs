@@ -0,0 +1,316 @@
+//! Export the current database into a SurrealQL dump, and import such dumps
+//! back, without the `surreal` CLI.
+//!
+//! ```rust,ignore
+//! let mut file = tokio::fs::File::create("backup.surql").await?;
+//! client.export(&mut file).await?;
+//!
+//! let file = tokio::fs::File::open("backup.surql").await?;
+//! client
+//!   .import_with_progress(file, DEFAULT_IMPORT_CHUNK_SIZE, |progress| {
+//!     println!("{} statements, {} bytes", progress.statements_done, progress.bytes_read)
+//!   })
+//!   .await?;
+//! ```
+//!
+//! The records are rendered as SurrealQL by the instance itself, so their
+//! links to other records, datetimes and durations keep their type. The dump
+//! starts with an `OPTION IMPORT` statement, like the dumps of the `surreal`
+//! CLI, so the events of the tables don't run again during the import.
+
+use std::fmt::Display;
+
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+
+use crate::record_id::table_identifier;
+use crate::rpc::RpcChannelError;
+use crate::schema::read_definitions;
+use crate::schema::DefinitionKind;
+use crate::syntax;
+use crate::RecordId;
+use crate::SurrealClient;
+
+/// The amount of records fetched, and written in a single `INSERT` statement,
+/// at once during an export.
+pub const DEFAULT_EXPORT_PAGE_SIZE: usize = 1000;
+
+/// The amount of statements sent in a single transaction during an import.
+pub const DEFAULT_IMPORT_CHUNK_SIZE: usize = 100;
+
+pub type DumpResult<T> = Result<T, DumpError>;
+
+#[derive(Debug, Error)]
+pub enum DumpError {
+  Rpc {
+    inner: RpcChannelError,
+  },
+  Io {
+    inner: std::io::Error,
+  },
+
+  /// A chunk of the dump failed to import, the chunks before it were
+  /// committed.
+  Import {
+    statements_done: usize,
+    inner: Box<RpcChannelError>,
+  },
+}
+
+impl From<RpcChannelError> for DumpError {
+  fn from(inner: RpcChannelError) -> Self {
+    Self::Rpc { inner }
+  }
+}
+
+impl From<std::io::Error> for DumpError {
+  fn from(inner: std::io::Error) -> Self {
+    Self::Io { inner }
+  }
+}
+
+impl Display for DumpError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      DumpError::Rpc { inner } => write!(f, "Dump RPC error: {inner}"),
+      DumpError::Io { inner } => write!(f, "Dump IO error: {inner}"),
+      DumpError::Import {
+        statements_done,
+        inner,
+      } => write!(
+        f,
+        "Failed to import the dump after {statements_done} statements: {inner}"
+      ),
+    }
+  }
+}
+
+/// The progress of an import, reported after every chunk. The dump is read
+/// as it is imported, so the total amount of statements is not known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImportProgress {
+  pub statements_done: usize,
+
+  /// The bytes of the dump that were read so far.
+  pub bytes_read: usize,
+}
+
+/// A record as it is read during an export, rendered as SurrealQL by the
+/// instance. The `in` and `out` are only set for the edges.
+#[derive(Deserialize)]
+struct ExportedRecord {
+  #[serde(rename = "in")]
+  from: Option<Value>,
+  #[serde(rename = "out")]
+  to: Option<Value>,
+  surql: String,
+}
+
+pub(crate) async fn export<W: AsyncWrite + Unpin>(
+  client: &mut SurrealClient, mut writer: W, page_size: usize,
+) -> DumpResult<()> {
+  let definitions = read_definitions(client, None).await?;
+  let tables: Vec<String> = definitions
+    .keys()
+    .filter(|key| key.kind == DefinitionKind::Table)
+    .map(|key| key.name.clone())
+    .collect();
+
+  writer.write_all(section("OPTION").as_bytes()).await?;
+  writer.write_all(b"OPTION IMPORT;\n").await?;
+  writer.write_all(section("DEFINITIONS").as_bytes()).await?;
+
+  for statement in definitions.values() {
+    writer
+      .write_all(format!("{statement};\n").as_bytes())
+      .await?;
+  }
+
+  for table in tables {
+    writer
+      .write_all(section(&format!("TABLE DATA: {table}")).as_bytes())
+      .await?;
+
+    let pages = client
+      .stream::<ExportedRecord>(
+        "SELECT in, out, <string> $this AS surql FROM type::table($table)".to_owned(),
+        json!({ "table": table }),
+        page_size,
+      )
+      .chunks(page_size);
+    futures::pin_mut!(pages);
+
+    while let Some(page) = pages.next().await {
+      let rows = page.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+      writer
+        .write_all(records_statements(&table, rows).as_bytes())
+        .await?;
+    }
+  }
+
+  writer.flush().await?;
+
+  Ok(())
+}
+
+pub(crate) async fn import<R: AsyncRead + Unpin, F: FnMut(ImportProgress)>(
+  client: &mut SurrealClient, reader: R, chunk_size: usize, mut on_progress: F,
+) -> DumpResult<()> {
+  let mut reader = BufReader::new(reader);
+  let mut importer = Importer {
+    client,
+    chunk_size: chunk_size.max(1),
+    options: Vec::new(),
+    chunk: Vec::new(),
+    progress: ImportProgress {
+      statements_done: 0,
+      bytes_read: 0,
+    },
+  };
+
+  // the dump is read a line at a time, the statements are imported once their
+  // `;` is read and the rest is kept until the next lines complete it.
+  let mut buffer = String::new();
+
+  loop {
+    let read = reader.read_line(&mut buffer).await?;
+    importer.progress.bytes_read += read;
+
+    if read == 0 {
+      let rest = syntax::split_statements(&buffer);
+      importer.push(rest, &mut on_progress).await?;
+      importer.flush(&mut on_progress).await?;
+
+      return Ok(());
+    }
+
+    let (statements, end) = syntax::complete_statements(&buffer);
+
+    if !statements.is_empty() {
+      importer.push(statements, &mut on_progress).await?;
+      buffer.drain(..end);
+    }
+  }
+}
+
+/// Groups the statements of a dump into transactions of `chunk_size`
+/// statements as they are read.
+struct Importer<'a> {
+  client: &'a mut SurrealClient,
+  chunk_size: usize,
+
+  /// The `OPTION` statements of the dump, they only apply to the query they
+  /// are part of so they are repeated before every transaction.
+  options: Vec<String>,
+  chunk: Vec<String>,
+  progress: ImportProgress,
+}
+
+impl<'a> Importer<'a> {
+  async fn push<F: FnMut(ImportProgress)>(
+    &mut self, statements: Vec<&str>, on_progress: &mut F,
+  ) -> DumpResult<()> {
+    for statement in statements {
+      match first_keyword(statement).as_str() {
+        // the statements are wrapped in transactions of their own
+        "begin" | "commit" | "cancel" => continue,
+        "option" => self.options.push(statement.to_owned()),
+        _ => self.chunk.push(statement.to_owned()),
+      };
+
+      if self.chunk.len() >= self.chunk_size {
+        self.flush(on_progress).await?;
+      }
+    }
+
+    Ok(())
+  }
+
+  async fn flush<F: FnMut(ImportProgress)>(&mut self, on_progress: &mut F) -> DumpResult<()> {
+    if self.chunk.is_empty() {
+      return Ok(());
+    }
+
+    let options: String = self
+      .options
+      .iter()
+      .map(|option| format!("{option};\n"))
+      .collect();
+    let query = format!(
+      "{options}BEGIN TRANSACTION;\n{};\nCOMMIT TRANSACTION;",
+      self.chunk.join(";\n")
+    );
+
+    self
+      .client
+      .send_retryable_query(query, json!({}), false)
+      .await
+      .map_err(|inner| DumpError::Import {
+        statements_done: self.progress.statements_done,
+        inner: Box::new(inner),
+      })?;
+
+    self.progress.statements_done += self.chunk.len();
+    self.chunk.clear();
+    on_progress(self.progress);
+
+    Ok(())
+  }
+}
+
+fn section(title: &str) -> String {
+  format!("\n-- ------------------------------\n-- {title}\n-- ------------------------------\n\n")
+}
+
+fn first_keyword(statement: &str) -> String {
+  statement
+    .split_whitespace()
+    .next()
+    .unwrap_or_default()
+    .to_lowercase()
+}
+
+/// The statements that create the `records` of the `table`: a single `INSERT`
+/// for the records, and a `RELATE` for every edge so they are linked again.
+fn records_statements(table: &str, records: Vec<ExportedRecord>) -> String {
+  let table = table_identifier(table);
+
+  let mut statements = String::new();
+  let mut contents = Vec::new();
+
+  for record in records {
+    match (record_id(record.from), record_id(record.to)) {
+      (Some(from), Some(to)) => statements.push_str(&format!(
+        "RELATE {from}->{table}->{to} CONTENT {};\n",
+        record.surql
+      )),
+      _ => contents.push(record.surql),
+    };
+  }
+
+  if !contents.is_empty() {
+    statements.insert_str(
+      0,
+      &format!("INSERT INTO {table} [{}];\n", contents.join(", ")),
+    );
+  }
+
+  statements
+}
+
+/// The record id an edge points to, as it was rendered by the instance.
+fn record_id(value: Option<Value>) -> Option<String> {
+  match value? {
+    Value::String(id) if id.parse::<RecordId>().is_ok() => Some(id),
+    _ => None,
+  }
+}
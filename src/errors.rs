@@ -12,3 +12,371 @@ pub struct SurrealInternalError {
   code: i32,
   message: String,
 }
+
+impl SurrealError {
+  /// The id of the request that caused the error
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  pub fn code(&self) -> i32 {
+    self.error.code
+  }
+
+  pub fn message(&self) -> &str {
+    &self.error.message
+  }
+}
+
+/// The error of a statement that failed to execute, parsed out of the message
+/// sent by SurrealDB so it can be matched on.
+///
+/// Every variant keeps the original `message`, and the messages that are not
+/// recognized end up in [`StatementError::Other`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatementError {
+  /// A unique index already contains the value.
+  UniqueViolation {
+    index: String,
+    value: String,
+    record: Option<String>,
+    message: String,
+  },
+  /// A record with the same id already exists.
+  RecordAlreadyExists {
+    record: Option<String>,
+    message: String,
+  },
+  PermissionDenied {
+    message: String,
+  },
+  /// A value doesn't conform to the `ASSERT` clause of its field.
+  FieldAssertion {
+    field: String,
+    value: String,
+    assertion: String,
+    record: Option<String>,
+    message: String,
+  },
+  /// A record that the statement expected to exist doesn't.
+  RecordNotFound {
+    record: Option<String>,
+    message: String,
+  },
+  /// A record that is used as an edge, or traversed as one, is not an edge.
+  NotARelation {
+    record: Option<String>,
+    message: String,
+  },
+  /// The line and column are 1-based. The snippet shows the failing line of
+  /// the query with a caret under the failing token, it is only available when
+  /// the error comes from a query sent by the client.
+  Parse {
+    line: Option<usize>,
+    column: Option<usize>,
+    snippet: Option<String>,
+    message: String,
+  },
+  /// The transaction conflicted with another one, it can be retried.
+  TransactionConflict {
+    message: String,
+  },
+  Other {
+    message: String,
+  },
+}
+
+impl StatementError {
+  pub fn from_message<M: Into<String>>(message: M) -> Self {
+    let message: String = message.into();
+    let lowercase = message.to_lowercase();
+    let quoted = backticked(&message);
+
+    if lowercase.contains("already contains") {
+      let value = between(&message, "already contains ", ", with record")
+        .or_else(|| message.split_once("already contains ").map(|(_, v)| v))
+        .unwrap_or_default();
+
+      return Self::UniqueViolation {
+        index: quoted.first().cloned().unwrap_or_default(),
+        value: unquote(value).to_owned(),
+        record: after(&message, "with record").and_then(|r| backticked(r).into_iter().next()),
+        message,
+      };
+    }
+
+    if lowercase.contains("already exists") {
+      return Self::RecordAlreadyExists {
+        record: quoted.first().cloned(),
+        message,
+      };
+    }
+
+    if lowercase.contains("must conform to") {
+      let value = between(&message, "Found ", " for field").unwrap_or_default();
+
+      return Self::FieldAssertion {
+        field: quoted.first().cloned().unwrap_or_default(),
+        value: unquote(value).to_owned(),
+        assertion: after(&message, "must conform to:")
+          .unwrap_or_default()
+          .trim()
+          .to_owned(),
+        record: after(&message, "with record").and_then(|r| backticked(r).into_iter().next()),
+        message,
+      };
+    }
+
+    if lowercase.contains("parse error") || lowercase.contains("failed to parse") {
+      let line = after(&lowercase, "line ").and_then(leading_number);
+      // older versions report a 0-based character rather than a column
+      let column = after(&lowercase, "column ")
+        .and_then(leading_number)
+        .or_else(|| {
+          after(&lowercase, "character ")
+            .and_then(leading_number)
+            .map(|c| c + 1)
+        });
+
+      return Self::Parse {
+        line,
+        column,
+        snippet: None,
+        message,
+      };
+    }
+
+    if lowercase.contains("permission") || lowercase.contains("not allowed") {
+      return Self::PermissionDenied { message };
+    }
+
+    if lowercase.contains("conflict")
+      || lowercase.contains("resource busy")
+      || lowercase.contains("can be retried")
+    {
+      return Self::TransactionConflict { message };
+    }
+
+    if lowercase.contains("not a relation") {
+      return Self::NotARelation {
+        record: quoted.first().cloned(),
+        message,
+      };
+    }
+
+    // only the records, a missing namespace, database or table is `Other`
+    if lowercase.contains("record `") && lowercase.contains("does not exist") {
+      return Self::RecordNotFound {
+        record: quoted.first().cloned(),
+        message,
+      };
+    }
+
+    Self::Other { message }
+  }
+
+  /// Attach the snippet of the `query` that failed to parse, if the error is a
+  /// [`StatementError::Parse`] with a known location.
+  pub fn with_query_snippet(mut self, query: &str) -> Self {
+    if let StatementError::Parse {
+      line: Some(line),
+      column: Some(column),
+      snippet,
+      ..
+    } = &mut self
+    {
+      *snippet = Some(crate::syntax::caret_snippet(query, *line, *column));
+    }
+
+    self
+  }
+
+  /// The message as it was sent by SurrealDB.
+  pub fn message(&self) -> &str {
+    match self {
+      StatementError::UniqueViolation { message, .. }
+      | StatementError::RecordAlreadyExists { message, .. }
+      | StatementError::PermissionDenied { message }
+      | StatementError::FieldAssertion { message, .. }
+      | StatementError::RecordNotFound { message, .. }
+      | StatementError::NotARelation { message, .. }
+      | StatementError::Parse { message, .. }
+      | StatementError::TransactionConflict { message }
+      | StatementError::Other { message } => message,
+    }
+  }
+}
+
+impl std::fmt::Display for StatementError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.message())?;
+
+    if let StatementError::Parse {
+      snippet: Some(snippet),
+      ..
+    } = self
+    {
+      write!(f, "\n{snippet}")?;
+    }
+
+    Ok(())
+  }
+}
+
+/// What is wrong with a query that failed the client-side syntax checks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxErrorKind {
+  UnterminatedString,
+  UnterminatedComment,
+  /// A closing delimiter that doesn't match the last opened one, `expected`
+  /// is [None] if no delimiter was opened.
+  UnexpectedDelimiter {
+    found: char,
+    expected: Option<char>,
+  },
+  UnclosedDelimiter {
+    open: char,
+  },
+  /// A parameter that has no value, neither in the query parameters nor in the
+  /// query itself.
+  UnknownParam {
+    name: String,
+  },
+  /// A query paginated by [`crate::SurrealClient::stream()`] that is made of
+  /// more than one statement.
+  MultipleStatements,
+}
+
+impl std::fmt::Display for SyntaxErrorKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SyntaxErrorKind::UnterminatedString => write!(f, "unterminated string"),
+      SyntaxErrorKind::UnterminatedComment => write!(f, "unterminated comment"),
+      SyntaxErrorKind::UnexpectedDelimiter {
+        found,
+        expected: Some(expected),
+      } => write!(f, "found `{found}` but expected `{expected}`"),
+      SyntaxErrorKind::UnexpectedDelimiter {
+        found,
+        expected: None,
+      } => write!(f, "unexpected `{found}`"),
+      SyntaxErrorKind::UnclosedDelimiter { open } => write!(f, "`{open}` is never closed"),
+      SyntaxErrorKind::UnknownParam { name } => write!(f, "unknown parameter ${name}"),
+      SyntaxErrorKind::MultipleStatements => {
+        write!(f, "a paginated query must be a single statement")
+      }
+    }
+  }
+}
+
+/// An error found in a query before sending it, refer to
+/// [`crate::SurrealClient::set_syntax_checks()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+  pub kind: SyntaxErrorKind,
+
+  /// The 1-based line and column of the error.
+  pub line: usize,
+  pub column: usize,
+
+  /// The failing line of the query with a caret under the error.
+  pub snippet: String,
+}
+
+impl SyntaxError {
+  pub(crate) fn new(kind: SyntaxErrorKind, query: &str, line: usize, column: usize) -> Self {
+    Self {
+      kind,
+      line,
+      column,
+      snippet: crate::syntax::caret_snippet(query, line, column),
+    }
+  }
+}
+
+impl std::fmt::Display for SyntaxError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{} at line {}, column {}\n{}",
+      self.kind, self.line, self.column, self.snippet
+    )
+  }
+}
+
+/// Get the segments of `message` that are surrounded by backticks.
+fn backticked(message: &str) -> Vec<String> {
+  message
+    .split('`')
+    .skip(1)
+    .step_by(2)
+    .map(str::to_owned)
+    .collect()
+}
+
+fn after<'a>(message: &'a str, pattern: &str) -> Option<&'a str> {
+  message
+    .find(pattern)
+    .map(|index| &message[index + pattern.len()..])
+}
+
+fn between<'a>(message: &'a str, start: &str, end: &str) -> Option<&'a str> {
+  let rest = after(message, start)?;
+
+  rest.find(end).map(|index| &rest[..index])
+}
+
+fn unquote(value: &str) -> &str {
+  let value = value.trim();
+
+  for quote in ['\'', '"', '`'] {
+    if let Some(inner) = value
+      .strip_prefix(quote)
+      .and_then(|v| v.strip_suffix(quote))
+    {
+      return inner;
+    }
+  }
+
+  value
+}
+
+fn leading_number(text: &str) -> Option<usize> {
+  let digits: String = text.chars().take_while(char::is_ascii_digit).collect();
+
+  digits.parse().ok()
+}
+
+/// The error returned when parsing a [RecordId](crate::RecordId) out of a
+/// string that isn't in the `table:id` form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRecordIdError {
+  pub value: String,
+}
+
+impl std::fmt::Display for ParseRecordIdError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "`{}` is not a record id of the form `table:id`",
+      self.value
+    )
+  }
+}
+
+impl std::error::Error for ParseRecordIdError {}
+
+/// The error returned when parsing a [ServerVersion](crate::ServerVersion) out
+/// of a string that isn't a `major.minor.patch` version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseServerVersionError {
+  pub value: String,
+}
+
+impl std::fmt::Display for ParseServerVersionError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "`{}` is not a SurrealDB version", self.value)
+  }
+}
+
+impl std::error::Error for ParseServerVersionError {}
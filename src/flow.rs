@@ -0,0 +1,211 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+
+use crate::rpc::RpcChannelError;
+use crate::rpc::RpcResult;
+
+/// The amount of requests that can wait in the outgoing queue of a
+/// [SurrealClient](crate::SurrealClient) before being written to the socket,
+/// sending a request waits for a free slot once the queue is full.
+pub const OUTGOING_QUEUE_CAPACITY: usize = 256;
+
+/// Describes how many requests a [SurrealClient](crate::SurrealClient) sends
+/// without having received their response, and what happens to the requests
+/// over that limit.
+///
+/// A request counts against the limit from the moment it is queued until its
+/// response is received, whether or not the response is awaited.
+#[derive(Debug, Clone, Default)]
+pub struct FlowControl {
+  /// The maximum amount of requests awaiting their response at once. There is
+  /// no limit if [None], which is the flow control of new clients.
+  pub max_in_flight: Option<usize>,
+
+  /// Whether the requests over the limit fail immediately with a
+  /// [`RpcChannelError::Overloaded`], rather than wait for a response to free
+  /// a slot.
+  pub reject_when_full: bool,
+}
+
+impl FlowControl {
+  /// Never limit the amount of requests in flight.
+  pub fn unlimited() -> Self {
+    Self::default()
+  }
+
+  /// Send at most `max_in_flight` requests at once, the next ones wait for a
+  /// slot to be freed.
+  pub fn wait(max_in_flight: usize) -> Self {
+    Self {
+      max_in_flight: Some(max_in_flight),
+      reject_when_full: false,
+    }
+  }
+
+  /// Send at most `max_in_flight` requests at once, the next ones fail with a
+  /// [`RpcChannelError::Overloaded`].
+  pub fn reject(max_in_flight: usize) -> Self {
+    Self {
+      max_in_flight: Some(max_in_flight),
+      reject_when_full: true,
+    }
+  }
+}
+
+/// A snapshot of the requests of a [SurrealClient](crate::SurrealClient),
+/// refer to [`SurrealClient::metrics()`](crate::SurrealClient::metrics).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientMetrics {
+  /// The requests that await their response, including the ones that are
+  /// still queued.
+  pub in_flight: usize,
+
+  /// The requests waiting in the outgoing queue to be written to the socket.
+  pub queued: usize,
+
+  pub max_in_flight: Option<usize>,
+  pub queue_capacity: usize,
+
+  /// The messages received since the connection was opened that matched no
+  /// pending request, like responses with an unknown id or frames that are
+  /// not responses at all. They are dropped after being counted.
+  pub unmatched_responses: usize,
+}
+
+/// The amount of permits of the requests when they are not limited: the most a
+/// semaphore holds, which is less than `u32::MAX` on the 32-bit targets, and
+/// at most the amount of permits that can be acquired at once.
+const UNLIMITED_PERMITS: usize = if Semaphore::MAX_PERMITS < u32::MAX as usize {
+  Semaphore::MAX_PERMITS
+} else {
+  u32::MAX as usize
+};
+
+/// The [FlowControl] of a client along with the permits of its requests.
+///
+/// The same semaphore is kept for the lifetime of the client and resized when
+/// the flow control changes, so the permits held by the requests in flight
+/// keep counting against the new limit.
+pub(crate) struct InFlightLimit {
+  flow_control: FlowControl,
+  permits: Arc<Semaphore>,
+
+  /// The total amount of permits of the semaphore, whether they are held or
+  /// not.
+  size: usize,
+
+  /// The permits that are forgotten rather than released once their request
+  /// is done, because the limit was lowered while they were held.
+  debt: Arc<AtomicUsize>,
+}
+
+impl InFlightLimit {
+  pub(crate) fn new(flow_control: FlowControl) -> Self {
+    let size = permits_of(&flow_control);
+
+    Self {
+      flow_control,
+      permits: Arc::new(Semaphore::new(size)),
+      size,
+      debt: Arc::new(AtomicUsize::new(0)),
+    }
+  }
+
+  pub(crate) fn flow_control(&self) -> &FlowControl {
+    &self.flow_control
+  }
+
+  /// Change the limit of the requests, the requests in flight are counted
+  /// against the new limit.
+  pub(crate) fn set_flow_control(&mut self, flow_control: FlowControl) {
+    let size = permits_of(&flow_control);
+
+    if size > self.size {
+      // the permits that were still owed are the first to be given back
+      let mut added = size - self.size;
+      let repaid = self
+        .debt
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |debt| {
+          Some(debt - debt.min(added))
+        })
+        .map(|debt| debt.min(added))
+        .unwrap_or_default();
+
+      added -= repaid;
+      self.permits.add_permits(added);
+    } else if size < self.size {
+      let removed = self.size - size;
+      let available = self.permits.available_permits().min(removed);
+
+      if let Ok(permits) = self.permits.try_acquire_many(available as u32) {
+        permits.forget();
+      }
+
+      self.debt.fetch_add(removed - available, Ordering::SeqCst);
+    }
+
+    self.size = size;
+    self.flow_control = flow_control;
+  }
+
+  /// Get the permit of a new request, which must be kept until its response
+  /// is received.
+  pub(crate) async fn acquire(&self) -> RpcResult<InFlightPermit> {
+    let permit = match self.flow_control.reject_when_full {
+      true => {
+        self
+          .permits
+          .clone()
+          .try_acquire_owned()
+          .map_err(|_| RpcChannelError::Overloaded {
+            max_in_flight: self.size,
+          })?
+      }
+      // the semaphore is never closed
+      false => self
+        .permits
+        .clone()
+        .acquire_owned()
+        .await
+        .map_err(|_| RpcChannelError::ClientClosed)?,
+    };
+
+    Ok(InFlightPermit {
+      permit: Some(permit),
+      debt: self.debt.clone(),
+    })
+  }
+}
+
+fn permits_of(flow_control: &FlowControl) -> usize {
+  flow_control
+    .max_in_flight
+    .map(|max_in_flight| max_in_flight.clamp(1, UNLIMITED_PERMITS))
+    .unwrap_or(UNLIMITED_PERMITS)
+}
+
+/// The permit held by a request until its response is received, see
+/// [InFlightLimit].
+pub(crate) struct InFlightPermit {
+  permit: Option<OwnedSemaphorePermit>,
+  debt: Arc<AtomicUsize>,
+}
+
+impl Drop for InFlightPermit {
+  fn drop(&mut self) {
+    let owed = self
+      .debt
+      .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |debt| {
+        debt.checked_sub(1)
+      })
+      .is_ok();
+
+    if let (true, Some(permit)) = (owed, self.permit.take()) {
+      permit.forget();
+    }
+  }
+}
@@ -0,0 +1,83 @@
+use serde_json::json;
+use serde_json::Value;
+
+use crate::record_id::is_plain_identifier;
+use crate::rpc::RpcChannelError;
+use crate::rpc::RpcResult;
+use crate::RecordId;
+
+/// The direction of a graph traversal.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Direction {
+  Outgoing,
+  Incoming,
+}
+
+impl Direction {
+  fn arrow(&self) -> &'static str {
+    match self {
+      Direction::Outgoing => "->",
+      Direction::Incoming => "<-",
+    }
+  }
+}
+
+/// The key under which the traversed records are selected.
+pub(crate) const TRAVERSAL_KEY: &str = "traversed";
+
+/// Confirm the `name` of a table can be written in a query as is.
+pub(crate) fn table_name(name: &str) -> RpcResult<&str> {
+  match is_plain_identifier(name) {
+    true => Ok(name),
+    false => Err(RpcChannelError::InvalidBinding {
+      message: format!("`{name}` is not a valid table name"),
+    }),
+  }
+}
+
+/// Build the query that relates `from` to `to` through the `edge`, along
+/// with its parameters. The record ids are bound rather than written in the
+/// query, the `$content` of the edge is left to the caller.
+pub(crate) fn relate_query(
+  from: &RecordId, edge: &str, to: &RecordId,
+) -> RpcResult<(String, Value)> {
+  let query = format!(
+    "RELATE (type::thing($from_table, $from_id))->{}->(type::thing($to_table, $to_id)) CONTENT $content",
+    table_name(edge)?
+  );
+  let params = json!({
+    "from_table": from.table(),
+    "from_id": from.id_param(),
+    "to_table": to.table(),
+    "to_id": to.id_param(),
+  });
+
+  Ok((query, params))
+}
+
+/// Build the query that selects the records found by following the `edge`
+/// from `record` in the given `direction`, and then the `node` table if
+/// supplied. The traversed records are fetched, along with their `fetch`
+/// fields. The id of the `record` is bound to the returned parameters.
+pub(crate) fn traversal_query(
+  record: &RecordId, direction: Direction, edge: &str, node: Option<&str>, fetch: &[&str],
+) -> RpcResult<(String, Value)> {
+  let arrow = direction.arrow();
+  let mut path = format!("{arrow}{}", table_name(edge)?);
+
+  if let Some(node) = node {
+    path.push_str(&format!("{arrow}{}", table_name(node)?));
+  }
+
+  let fetched: Vec<String> = std::iter::once(TRAVERSAL_KEY.to_owned())
+    .chain(fetch.iter().map(|field| format!("{TRAVERSAL_KEY}.{field}")))
+    .collect();
+
+  let query = format!(
+    "SELECT {path} AS {TRAVERSAL_KEY} FROM type::thing($table, $id) FETCH {}",
+    fetched.join(", ")
+  );
+  let params = json!({ "table": record.table(), "id": record.id_param() });
+
+  Ok((query, params))
+}
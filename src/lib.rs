@@ -1,9 +1,55 @@
+mod batch;
+mod bulk;
+mod compression;
+mod connection;
+mod flow;
+mod graph;
+mod link;
 mod message;
+mod pool;
+mod protocol;
+mod query;
+mod record_id;
 mod response;
+mod retry;
+mod scoped;
+mod stream;
 mod surreal_client;
+mod syntax;
+mod version;
 
+pub use batch::Batch;
+pub use batch::BatchResponse;
+pub use bulk::BulkInsertChunk;
+pub use bulk::DEFAULT_BULK_CONCURRENCY;
+pub use compression::Compression;
+pub use compression::CompressionStats;
+pub use flow::ClientMetrics;
+pub use flow::FlowControl;
+pub use flow::OUTGOING_QUEUE_CAPACITY;
+pub use link::Link;
+pub use link::LinkVec;
 pub use message::SurrealMessage;
+pub use pool::PooledClient;
+pub use pool::SurrealPool;
+pub use protocol::Protocol;
+pub use query::Binding;
+pub use query::Query;
+pub use record_id::RecordId;
 pub use response::SurrealResponseData;
+pub use retry::RetryPolicy;
+pub use scoped::ScopedClient;
 pub use surreal_client::SurrealClient;
+pub use syntax::check_query;
+pub use version::ServerVersion;
+pub use version::MIN_SERVER_VERSION;
+pub use version::UNSUPPORTED_SERVER_MAJOR;
+#[cfg(feature = "axum")]
+pub mod axum_support;
+pub mod dump;
 pub mod errors;
+pub mod migrations;
 pub mod rpc;
+pub mod schema;
+#[cfg(feature = "testing")]
+pub mod testing;
@@ -0,0 +1,144 @@
+use std::ops::Deref;
+
+use serde::de::DeserializeOwned;
+use serde::de::Error;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use serde_json::Value;
+
+use crate::RecordId;
+
+/// A field that links to another record, which holds either the id of the
+/// record or the whole record when it was fetched with a `FETCH` clause.
+///
+/// It deserializes from both forms, and always serializes back into the id of
+/// the record so the models can be sent back to the database as is:
+/// ```rust,ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct File {
+///   name: String,
+///   author: Link<User>,
+/// }
+///
+/// // select * from file fetch author
+/// if let Some(author) = file.author.loaded() {
+///   println!("{} was written by {}", file.name, author.name);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Link<T> {
+  Id(RecordId),
+  Loaded { id: RecordId, record: T },
+}
+
+impl<T> Link<T> {
+  pub fn id(&self) -> &RecordId {
+    match self {
+      Link::Id(id) => id,
+      Link::Loaded { id, record: _ } => id,
+    }
+  }
+
+  /// Get the record if it was fetched.
+  pub fn loaded(&self) -> Option<&T> {
+    match self {
+      Link::Id(_) => None,
+      Link::Loaded { id: _, record } => Some(record),
+    }
+  }
+
+  pub fn into_loaded(self) -> Option<T> {
+    match self {
+      Link::Id(_) => None,
+      Link::Loaded { id: _, record } => Some(record),
+    }
+  }
+
+  pub fn is_loaded(&self) -> bool {
+    matches!(self, Link::Loaded { .. })
+  }
+}
+
+impl<T> From<RecordId> for Link<T> {
+  fn from(id: RecordId) -> Self {
+    Link::Id(id)
+  }
+}
+
+impl<T> Serialize for Link<T> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.id().serialize(serializer)
+  }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Link<T> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+
+    match value {
+      Value::String(id) => Ok(Link::Id(id.parse().map_err(D::Error::custom)?)),
+      Value::Object(ref fields) => {
+        let id = fields
+          .get("id")
+          .and_then(Value::as_str)
+          .ok_or_else(|| D::Error::custom("a fetched record link must have an id"))?
+          .parse()
+          .map_err(D::Error::custom)?;
+
+        let record = serde_json::from_value(value).map_err(D::Error::custom)?;
+
+        Ok(Link::Loaded { id, record })
+      }
+      _ => Err(D::Error::custom(
+        "expected a record id or a fetched record for a link",
+      )),
+    }
+  }
+}
+
+/// A list of [Link]s, for the fields that link to many records.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+#[serde(bound(deserialize = "T: DeserializeOwned"))]
+pub struct LinkVec<T>(Vec<Link<T>>);
+
+impl<T> LinkVec<T> {
+  pub fn new(links: Vec<Link<T>>) -> Self {
+    Self(links)
+  }
+
+  pub fn ids(&self) -> Vec<&RecordId> {
+    self.0.iter().map(Link::id).collect()
+  }
+
+  /// Get the records if all of them were fetched.
+  pub fn loaded(&self) -> Option<Vec<&T>> {
+    self.0.iter().map(Link::loaded).collect()
+  }
+
+  pub fn into_inner(self) -> Vec<Link<T>> {
+    self.0
+  }
+}
+
+impl<T> Default for LinkVec<T> {
+  fn default() -> Self {
+    Self(Vec::new())
+  }
+}
+
+impl<T> Deref for LinkVec<T> {
+  type Target = [Link<T>];
+
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+
+impl<T> From<Vec<RecordId>> for LinkVec<T> {
+  fn from(ids: Vec<RecordId>) -> Self {
+    Self(ids.into_iter().map(Link::Id).collect())
+  }
+}
@@ -0,0 +1,505 @@
+//! Versioned schema migrations applied through a [SurrealClient].
+//!
+//! Every migration is applied inside its own transaction along with the record
+//! that marks it as applied in the [`MIGRATIONS_TABLE`] table, so a failing
+//! migration leaves no trace. The checksum of every applied migration is
+//! stored as well, so a migration that was edited after being applied is
+//! detected instead of being silently ignored.
+//!
+//! The migrations can't contain `BEGIN`, `COMMIT` or `CANCEL` statements
+//! since they are already wrapped in a transaction. And only one runner can
+//! apply or revert migrations at a time: the runner holds a lock record in the
+//! [`MIGRATIONS_LOCK_TABLE`] table while it changes the database, the other
+//! runners fail with [`MigrationError::Locked`] in the meantime.
+//!
+//! ```rust,ignore
+//! let migrator = Migrator::from_directory("./migrations")?;
+//!
+//! // list the versions that would be applied, without applying them
+//! let pending = migrator.clone().dry_run(true).up(&mut client).await?;
+//!
+//! migrator.up(&mut client).await?;
+//! ```
+
+use std::fmt::Display;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use thiserror::Error;
+
+use crate::errors::StatementError;
+use crate::rpc::RpcChannelError;
+use crate::syntax;
+use crate::SurrealClient;
+
+/// The table where the applied migrations are recorded.
+pub const MIGRATIONS_TABLE: &str = "_migrations";
+
+/// The table of the lock record, held while the migrations are applied or
+/// reverted.
+pub const MIGRATIONS_LOCK_TABLE: &str = "_migrations_lock";
+
+/// The id of the lock record in the [`MIGRATIONS_LOCK_TABLE`] table.
+const LOCK_ID: &str = "lock";
+
+pub type MigrationResult<T> = Result<T, MigrationError>;
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+  Rpc {
+    inner: RpcChannelError,
+  },
+  Io {
+    path: PathBuf,
+    inner: std::io::Error,
+  },
+  InvalidFileName {
+    path: PathBuf,
+  },
+  DuplicateVersion {
+    version: u64,
+  },
+  ChecksumMismatch {
+    version: u64,
+    name: String,
+  },
+  UnknownAppliedMigration {
+    version: u64,
+  },
+  MissingDown {
+    version: u64,
+  },
+  MissingUp {
+    version: u64,
+  },
+
+  /// The migration has a `BEGIN`, `COMMIT` or `CANCEL` statement, while it is
+  /// already applied inside a transaction.
+  TransactionStatement {
+    version: u64,
+  },
+
+  /// Another runner is applying or reverting migrations. If that runner died
+  /// before releasing its lock, [`Migrator::break_lock()`] removes it.
+  Locked,
+}
+
+impl From<RpcChannelError> for MigrationError {
+  fn from(inner: RpcChannelError) -> Self {
+    Self::Rpc { inner }
+  }
+}
+
+impl Display for MigrationError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      MigrationError::Rpc { inner } => write!(f, "Migration RPC error: {inner}"),
+      MigrationError::Io { path, inner } => {
+        write!(f, "Failed to read migration {}: {inner}", path.display())
+      }
+      MigrationError::InvalidFileName { path } => write!(
+        f,
+        "Invalid migration file name {}, expected `<version>_<name>.surql` or `<version>_<name>.down.surql`",
+        path.display()
+      ),
+      MigrationError::DuplicateVersion { version } => {
+        write!(f, "Migration version {version} is defined more than once")
+      }
+      MigrationError::ChecksumMismatch { version, name } => write!(
+        f,
+        "Migration {version} ({name}) was edited after being applied"
+      ),
+      MigrationError::UnknownAppliedMigration { version } => write!(
+        f,
+        "Migration {version} was applied to the database but is not defined anymore"
+      ),
+      MigrationError::MissingDown { version } => {
+        write!(f, "Migration {version} has no down migration")
+      }
+      MigrationError::MissingUp { version } => {
+        write!(f, "Migration {version} has a down migration but no up migration")
+      }
+      MigrationError::TransactionStatement { version } => write!(
+        f,
+        "Migration {version} has a BEGIN, COMMIT or CANCEL statement but migrations are already applied in a transaction"
+      ),
+      MigrationError::Locked => write!(
+        f,
+        "Another runner holds the {MIGRATIONS_LOCK_TABLE}:{LOCK_ID} lock, migrations can't be applied or reverted meanwhile"
+      ),
+    }
+  }
+}
+
+/// A single versioned migration.
+#[derive(Debug, Clone)]
+pub struct Migration {
+  pub version: u64,
+  pub name: String,
+
+  /// The statements that apply the migration.
+  pub up: String,
+
+  /// The statements that revert the migration, if it can be reverted.
+  pub down: Option<String>,
+}
+
+impl Migration {
+  pub fn new<N: Into<String>, U: Into<String>>(version: u64, name: N, up: U) -> Self {
+    Self {
+      version,
+      name: name.into(),
+      up: up.into(),
+      down: None,
+    }
+  }
+
+  pub fn with_down<D: Into<String>>(mut self, down: D) -> Self {
+    self.down = Some(down.into());
+    self
+  }
+
+  /// A FNV-1a hash of the `up` statements, line endings are normalized so the
+  /// checksum doesn't change with the platform the file was checked out on.
+  pub fn checksum(&self) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for byte in self.up.replace("\r\n", "\n").trim().bytes() {
+      hash ^= byte as u64;
+      hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    format!("{hash:016x}")
+  }
+}
+
+/// A migration as it is recorded in the [`MIGRATIONS_TABLE`] table.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppliedMigration {
+  pub version: u64,
+  pub name: String,
+  pub checksum: String,
+}
+
+/// Applies and reverts a set of [Migration]s in order of version.
+#[derive(Debug, Clone)]
+pub struct Migrator {
+  migrations: Vec<Migration>,
+  dry_run: bool,
+}
+
+impl Migrator {
+  pub fn new(mut migrations: Vec<Migration>) -> MigrationResult<Self> {
+    migrations.sort_by_key(|migration| migration.version);
+
+    if let Some(pair) = migrations
+      .windows(2)
+      .find(|pair| pair[0].version == pair[1].version)
+    {
+      return Err(MigrationError::DuplicateVersion {
+        version: pair[0].version,
+      });
+    }
+
+    if let Some(migration) = migrations.iter().find(|migration| {
+      has_transaction_statement(&migration.up)
+        || migration
+          .down
+          .as_deref()
+          .is_some_and(has_transaction_statement)
+    }) {
+      return Err(MigrationError::TransactionStatement {
+        version: migration.version,
+      });
+    }
+
+    Ok(Self {
+      migrations,
+      dry_run: false,
+    })
+  }
+
+  /// Load the migrations from the `.surql` files in `directory`. The files are
+  /// expected to be named `<version>_<name>.surql`, and the optional down
+  /// migrations `<version>_<name>.down.surql`.
+  pub fn from_directory<P: AsRef<Path>>(directory: P) -> MigrationResult<Self> {
+    let directory = directory.as_ref();
+    let io_error = |path: &Path| {
+      let path = path.to_owned();
+
+      move |inner| MigrationError::Io { path, inner }
+    };
+
+    let mut migrations: Vec<Migration> = Vec::new();
+    let mut downs: Vec<(u64, String)> = Vec::new();
+
+    for entry in std::fs::read_dir(directory).map_err(io_error(directory))? {
+      let path = entry.map_err(io_error(directory))?.path();
+      let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+
+      let Some(stem) = file_name.strip_suffix(".surql") else {
+        continue;
+      };
+
+      let (stem, is_down) = match stem.strip_suffix(".down") {
+        Some(stem) => (stem, true),
+        None => (stem, false),
+      };
+
+      let (version, name) = stem
+        .split_once('_')
+        .and_then(|(version, name)| Some((version.parse::<u64>().ok()?, name)))
+        .ok_or_else(|| MigrationError::InvalidFileName { path: path.clone() })?;
+
+      let content = std::fs::read_to_string(&path).map_err(io_error(&path))?;
+
+      match is_down {
+        true => downs.push((version, content)),
+        false => migrations.push(Migration::new(version, name, content)),
+      };
+    }
+
+    for (version, down) in downs {
+      match migrations.iter_mut().find(|m| m.version == version) {
+        Some(migration) => migration.down = Some(down),
+        None => return Err(MigrationError::MissingUp { version }),
+      }
+    }
+
+    Self::new(migrations)
+  }
+
+  /// When enabled, [`Self::up()`] and [`Self::down()`] return the versions
+  /// they would apply or revert without changing the database.
+  pub fn dry_run(mut self, dry_run: bool) -> Self {
+    self.dry_run = dry_run;
+    self
+  }
+
+  pub fn migrations(&self) -> &[Migration] {
+    &self.migrations
+  }
+
+  /// Get the migrations recorded in the database, in order of version.
+  pub async fn applied(
+    &self, client: &mut SurrealClient,
+  ) -> MigrationResult<Vec<AppliedMigration>> {
+    let applied = client
+      .find_many(
+        format!("SELECT version, name, checksum FROM {MIGRATIONS_TABLE} ORDER BY version"),
+        json!({}),
+      )
+      .await?;
+
+    Ok(applied)
+  }
+
+  /// Confirm every applied migration is still defined and wasn't edited since
+  /// it was applied.
+  pub async fn verify(&self, client: &mut SurrealClient) -> MigrationResult<Vec<AppliedMigration>> {
+    let applied = self.applied(client).await?;
+
+    for record in &applied {
+      let migration = self
+        .migrations
+        .iter()
+        .find(|migration| migration.version == record.version)
+        .ok_or(MigrationError::UnknownAppliedMigration {
+          version: record.version,
+        })?;
+
+      if migration.checksum() != record.checksum {
+        return Err(MigrationError::ChecksumMismatch {
+          version: migration.version,
+          name: migration.name.clone(),
+        });
+      }
+    }
+
+    Ok(applied)
+  }
+
+  /// Apply every migration that wasn't applied yet, in order of version, and
+  /// return their versions.
+  pub async fn up(&self, client: &mut SurrealClient) -> MigrationResult<Vec<u64>> {
+    let owner = self.lock(client).await?;
+    let result = self.apply_up(client).await;
+    self.unlock(client, owner).await?;
+
+    result
+  }
+
+  /// Revert the applied migrations whose version is greater than `target`, in
+  /// reverse order of version, and return their versions.
+  pub async fn down(&self, client: &mut SurrealClient, target: u64) -> MigrationResult<Vec<u64>> {
+    let owner = self.lock(client).await?;
+    let result = self.apply_down(client, target).await;
+    self.unlock(client, owner).await?;
+
+    result
+  }
+
+  /// Remove the lock record whoever holds it, for when a runner died while it
+  /// was holding the lock. It must not be called while a runner is alive.
+  pub async fn break_lock(&self, client: &mut SurrealClient) -> MigrationResult<()> {
+    client
+      .send_checked_query(
+        "DELETE type::thing($table, $id)".to_owned(),
+        json!({ "table": MIGRATIONS_LOCK_TABLE, "id": LOCK_ID }),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  /// Create the lock record and return the random owner it was created with.
+  /// Nothing is locked in [dry run](Self::dry_run) mode since the database
+  /// isn't changed.
+  async fn lock(&self, client: &mut SurrealClient) -> MigrationResult<Option<String>> {
+    if self.dry_run {
+      return Ok(None);
+    }
+
+    let owner = nanoid::nanoid!();
+    let result = client
+      .send_checked_query(
+        "CREATE type::thing($table, $id) CONTENT { owner: $owner, locked_at: time::now() }"
+          .to_owned(),
+        json!({ "table": MIGRATIONS_LOCK_TABLE, "id": LOCK_ID, "owner": owner }),
+      )
+      .await;
+
+    match result {
+      Ok(_) => Ok(Some(owner)),
+      Err(error) => match error.statement_error() {
+        Some(StatementError::RecordAlreadyExists { .. }) => Err(MigrationError::Locked),
+        _ => Err(error.into()),
+      },
+    }
+  }
+
+  /// Remove the lock record, unless another runner broke the lock and now
+  /// owns it.
+  async fn unlock(&self, client: &mut SurrealClient, owner: Option<String>) -> MigrationResult<()> {
+    let Some(owner) = owner else {
+      return Ok(());
+    };
+
+    client
+      .send_checked_query(
+        "DELETE type::thing($table, $id) WHERE owner = $owner".to_owned(),
+        json!({ "table": MIGRATIONS_LOCK_TABLE, "id": LOCK_ID, "owner": owner }),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn apply_up(&self, client: &mut SurrealClient) -> MigrationResult<Vec<u64>> {
+    let applied = self.verify(client).await?;
+    let pending: Vec<&Migration> = self
+      .migrations
+      .iter()
+      .filter(|migration| !applied.iter().any(|a| a.version == migration.version))
+      .collect();
+
+    let mut versions = Vec::new();
+
+    for migration in pending {
+      if !self.dry_run {
+        let query = format!(
+          "BEGIN TRANSACTION;\n{}\nCREATE type::thing($table, $version) CONTENT {{ version: $version, name: $name, checksum: $checksum, applied_at: time::now() }};\nCOMMIT TRANSACTION;",
+          terminated(&migration.up)
+        );
+
+        client
+          .send_checked_query(
+            query,
+            json!({
+              "table": MIGRATIONS_TABLE,
+              "version": migration.version,
+              "name": migration.name,
+              "checksum": migration.checksum(),
+            }),
+          )
+          .await?;
+      }
+
+      versions.push(migration.version);
+    }
+
+    Ok(versions)
+  }
+
+  async fn apply_down(&self, client: &mut SurrealClient, target: u64) -> MigrationResult<Vec<u64>> {
+    let applied = self.verify(client).await?;
+    let mut versions = Vec::new();
+
+    for record in applied.iter().rev().filter(|a| a.version > target) {
+      let migration = self
+        .migrations
+        .iter()
+        .find(|migration| migration.version == record.version)
+        .ok_or(MigrationError::UnknownAppliedMigration {
+          version: record.version,
+        })?;
+
+      let down = migration.down.as_ref().ok_or(MigrationError::MissingDown {
+        version: migration.version,
+      })?;
+
+      if !self.dry_run {
+        let query = format!(
+          "BEGIN TRANSACTION;\n{}\nDELETE type::thing($table, $version);\nCOMMIT TRANSACTION;",
+          terminated(down)
+        );
+
+        client
+          .send_checked_query(
+            query,
+            json!({
+              "table": MIGRATIONS_TABLE,
+              "version": migration.version,
+            }),
+          )
+          .await?;
+      }
+
+      versions.push(migration.version);
+    }
+
+    Ok(versions)
+  }
+}
+
+/// Make sure every statement of `statements` is terminated by a `;` so more
+/// statements can be appended to them, even when the last one is followed by a
+/// comment.
+fn terminated(statements: &str) -> String {
+  syntax::split_statements(statements)
+    .into_iter()
+    .map(|statement| format!("{statement};"))
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+/// Whether `statements` begin, commit or cancel a transaction.
+fn has_transaction_statement(statements: &str) -> bool {
+  syntax::split_statements(statements)
+    .into_iter()
+    .any(|statement| {
+      let keyword = statement
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+      matches!(keyword.as_str(), "begin" | "commit" | "cancel")
+    })
+}
@@ -0,0 +1,269 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde_json::Map;
+use serde_json::Value;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+
+use crate::rpc::RpcChannelError;
+use crate::rpc::RpcResult;
+use crate::SurrealClient;
+
+type Connect = Box<dyn Fn() -> BoxFuture<'static, RpcResult<SurrealClient>> + Send + Sync>;
+
+/// A fixed set of [SurrealClient]s that can be shared between tasks, each
+/// client being used by a single task at a time.
+///
+/// The tasks wait in line for a client to be released when they are all in
+/// use, and a client whose connection was closed is replaced by a new one from
+/// the [connect](Self::with_connect) function before being handed out.
+///
+/// The clients are handed out with the session they had when they joined the
+/// pool: the namespace, database and variables a task changed are reset before
+/// the client is handed out again, and a client whose session can't be reset
+/// is replaced like a closed one.
+///
+/// Cloning the pool is cheap and returns a handle to the same clients.
+#[derive(Clone)]
+pub struct SurrealPool {
+  inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+  /// The clients that are not in use, there is one for every available permit
+  /// of the semaphore.
+  idle: Mutex<VecDeque<IdleClient>>,
+  semaphore: Arc<Semaphore>,
+  size: usize,
+  connect: Option<Connect>,
+}
+
+impl SurrealPool {
+  /// Create a pool out of clients that are already connected and signed in.
+  ///
+  /// # Panics
+  /// If `clients` is empty.
+  pub fn new(clients: Vec<SurrealClient>) -> Self {
+    assert!(
+      !clients.is_empty(),
+      "a SurrealPool needs at least one client"
+    );
+
+    let size = clients.len();
+    let idle = clients
+      .into_iter()
+      .map(|client| IdleClient {
+        session: Session::of(&client),
+        client,
+      })
+      .collect();
+
+    Self {
+      inner: Arc::new(PoolInner {
+        idle: Mutex::new(idle),
+        semaphore: Arc::new(Semaphore::new(size)),
+        size,
+        connect: None,
+      }),
+    }
+  }
+
+  /// Set the function that connects and signs in the clients that replace the
+  /// closed ones. Without it [`Self::get()`] fails with a
+  /// [`RpcChannelError::ClientClosed`] when the next client is closed.
+  ///
+  /// # Panics
+  /// If the pool was already cloned.
+  pub fn with_connect<F, Fut>(mut self, connect: F) -> Self
+  where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = RpcResult<SurrealClient>> + Send + 'static,
+  {
+    let inner = Arc::get_mut(&mut self.inner).expect("the SurrealPool was already cloned");
+    inner.connect = Some(Box::new(move || connect().boxed()));
+
+    self
+  }
+
+  pub fn size(&self) -> usize {
+    self.inner.size
+  }
+
+  /// Get a client that is not in use, or wait for one to be released if they
+  /// are all in use. The tasks are served in the order they called this
+  /// function.
+  ///
+  /// If the session of the client was changed since it joined the pool it is
+  /// reset first. If the connection of the client was closed, or if its
+  /// session can't be reset, it is replaced first. The error of the
+  /// [connect](Self::with_connect) function is returned if that fails and the
+  /// client is left in the pool for the next attempt.
+  pub async fn get(&self) -> RpcResult<PooledClient> {
+    let permit = self
+      .inner
+      .semaphore
+      .clone()
+      .acquire_owned()
+      .await
+      .expect("the semaphore of the SurrealPool is never closed");
+
+    let idle = self
+      .inner
+      .idle
+      .lock()
+      .unwrap()
+      .pop_front()
+      .expect("every permit of the SurrealPool has an idle client");
+
+    let mut pooled = PooledClient {
+      client: Some(idle.client),
+      session: idle.session,
+      pool: self.inner.clone(),
+      _permit: permit,
+    };
+
+    if pooled.is_closed() || !pooled.reset_session().await {
+      let connect = self
+        .inner
+        .connect
+        .as_ref()
+        .ok_or(RpcChannelError::ClientClosed)?;
+
+      *pooled = connect().await?;
+      pooled.session = Session::of(&pooled);
+    }
+
+    Ok(pooled)
+  }
+}
+
+/// A client waiting in the pool, along with the session it had when it joined
+/// the pool.
+struct IdleClient {
+  client: SurrealClient,
+  session: Session,
+}
+
+/// The namespace, database and variables of the session of a client.
+#[derive(Default, PartialEq)]
+struct Session {
+  scope: Option<(String, String)>,
+  vars: Map<String, Value>,
+}
+
+impl Session {
+  fn of(client: &SurrealClient) -> Self {
+    Self {
+      scope: client
+        .session_scope()
+        .map(|(namespace, database)| (namespace.to_owned(), database.to_owned())),
+      vars: client.session_vars().clone(),
+    }
+  }
+}
+
+/// A [SurrealClient] borrowed from a [SurrealPool], it returns to the pool
+/// once dropped.
+pub struct PooledClient {
+  client: Option<SurrealClient>,
+
+  /// The session the client had when it joined the pool.
+  session: Session,
+  pool: Arc<PoolInner>,
+
+  /// Released after the client is back in the idle queue, when the fields are
+  /// dropped.
+  _permit: OwnedSemaphorePermit,
+}
+
+impl PooledClient {
+  /// Bring the session of the client back to the one it had when it joined
+  /// the pool, and tell whether that succeeded. A session that had no
+  /// namespace can't leave the one it was switched to.
+  async fn reset_session(&mut self) -> bool {
+    let session = std::mem::take(&mut self.session);
+    let reset = reset_session(self, &session).await.unwrap_or(false);
+
+    self.session = session;
+
+    reset
+  }
+}
+
+async fn reset_session(client: &mut SurrealClient, session: &Session) -> RpcResult<bool> {
+  let scope = client
+    .session_scope()
+    .map(|(namespace, database)| (namespace.to_owned(), database.to_owned()));
+
+  if scope != session.scope {
+    let Some((namespace, database)) = session.scope.clone() else {
+      return Ok(false);
+    };
+
+    client.use_namespace(namespace, database).await?;
+  }
+
+  let changed: Vec<String> = client
+    .session_vars()
+    .iter()
+    .filter(|(name, value)| session.vars.get(*name) != Some(*value))
+    .map(|(name, _)| name.clone())
+    .collect();
+
+  for name in changed {
+    client.unset_var(&name).await?;
+  }
+
+  let missing: Map<String, Value> = session
+    .vars
+    .iter()
+    .filter(|(name, _)| !client.session_vars().contains_key(*name))
+    .map(|(name, value)| (name.clone(), value.clone()))
+    .collect();
+
+  client.restore_session_vars(missing).await?;
+
+  Ok(true)
+}
+
+impl Deref for PooledClient {
+  type Target = SurrealClient;
+
+  fn deref(&self) -> &Self::Target {
+    self
+      .client
+      .as_ref()
+      .expect("the client is only taken on drop")
+  }
+}
+
+impl DerefMut for PooledClient {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    self
+      .client
+      .as_mut()
+      .expect("the client is only taken on drop")
+  }
+}
+
+impl Drop for PooledClient {
+  fn drop(&mut self) {
+    if let Some(client) = self.client.take() {
+      let session = std::mem::take(&mut self.session);
+
+      self
+        .pool
+        .idle
+        .lock()
+        .unwrap()
+        .push_back(IdleClient { client, session });
+    }
+  }
+}
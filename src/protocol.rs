@@ -0,0 +1,263 @@
+use ciborium::value::Value as CborValue;
+use serde_json::Map;
+use serde_json::Number;
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::rpc::RpcChannelError;
+use crate::rpc::RpcResult;
+use crate::RecordId;
+use crate::SurrealMessage;
+
+/// The format of the RPC messages exchanged with the SurrealDB instance. It is
+/// negotiated through the websocket subprotocol when connecting, refer to
+/// [`SurrealClient::with_protocol()`](crate::SurrealClient::with_protocol).
+///
+/// JSON messages are sent in text frames, and CBOR messages in binary frames.
+/// CBOR keeps the datetimes, decimals and record ids exactly as the database
+/// returns them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+  Json,
+  Cbor,
+}
+
+impl Protocol {
+  /// The name of the websocket subprotocol.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Protocol::Json => "json",
+      Protocol::Cbor => "cbor",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name.trim() {
+      "json" => Some(Protocol::Json),
+      "cbor" => Some(Protocol::Cbor),
+      _ => None,
+    }
+  }
+
+  /// The value of the `Sec-WebSocket-Protocol` header when this protocol is
+  /// preferred, JSON is always offered as a fallback.
+  pub(crate) fn offered_subprotocols(&self) -> &'static str {
+    match self {
+      Protocol::Json => "json",
+      Protocol::Cbor => "cbor, json",
+    }
+  }
+
+  pub(crate) fn encode(&self, message: &SurrealMessage) -> RpcResult<Message> {
+    match self {
+      Protocol::Json => Ok(Message::Text(serde_json::to_string(message)?)),
+      Protocol::Cbor => {
+        let mut bytes = Vec::new();
+
+        ciborium::ser::into_writer(message, &mut bytes).map_err(|error| {
+          RpcChannelError::CborError {
+            message: error.to_string(),
+          }
+        })?;
+
+        Ok(Message::Binary(bytes))
+      }
+    }
+  }
+}
+
+/// Decode a frame received from the SurrealDB instance, whatever the protocol
+/// of the connection is. [None] is returned for the frames that don't hold a
+/// message.
+pub(crate) fn decode_frame(message: Message) -> Option<Value> {
+  match message {
+    Message::Text(json) => serde_json::from_str(&json).ok(),
+    Message::Binary(bytes) => {
+      let value: CborValue = ciborium::de::from_reader(bytes.as_slice()).ok()?;
+
+      Some(cbor_to_json(value))
+    }
+    _ => None,
+  }
+}
+
+/// Convert a CBOR value into JSON. The values SurrealDB tags are converted into
+/// the form they have in the JSON protocol: record ids are turned into their
+/// `table:id` string and `NONE` into `null`, while datetimes, decimals, uuids
+/// and durations keep their exact string form. The compact datetimes and
+/// durations, sent as `[seconds, nanoseconds]`, are turned into the strings
+/// SurrealDB would have sent for them.
+pub(crate) fn cbor_to_json(value: CborValue) -> Value {
+  match value {
+    CborValue::Integer(integer) => {
+      let integer = i128::from(integer);
+
+      u64::try_from(integer)
+        .map(Value::from)
+        .or_else(|_| i64::try_from(integer).map(Value::from))
+        .unwrap_or_else(|_| Value::String(integer.to_string()))
+    }
+    CborValue::Float(float) => Number::from_f64(float)
+      .map(Value::Number)
+      .unwrap_or(Value::Null),
+    CborValue::Bytes(bytes) => Value::Array(bytes.into_iter().map(Value::from).collect()),
+    CborValue::Text(text) => Value::String(text),
+    CborValue::Bool(boolean) => Value::Bool(boolean),
+    CborValue::Array(values) => Value::Array(values.into_iter().map(cbor_to_json).collect()),
+    CborValue::Map(entries) => {
+      let mut object = Map::new();
+
+      for (key, value) in entries {
+        let key = match cbor_to_json(key) {
+          Value::String(key) => key,
+          key => key.to_string(),
+        };
+
+        object.insert(key, cbor_to_json(value));
+      }
+
+      Value::Object(object)
+    }
+    // NONE
+    CborValue::Tag(6, _) => Value::Null,
+    // record id, as a `[table, id]` array
+    CborValue::Tag(8, inner) => match *inner {
+      CborValue::Array(parts) if parts.len() == 2 => {
+        let mut parts = parts.into_iter().map(cbor_to_json);
+        let (table, id) = (
+          parts.next().unwrap_or_default(),
+          parts.next().unwrap_or_default(),
+        );
+        let id = match (&table, id) {
+          (Value::String(table), Value::Number(id)) if id.is_i64() => {
+            RecordId::numeric(table, id.as_i64().unwrap_or_default())
+          }
+          (Value::String(table), Value::String(id)) => RecordId::new(table, id),
+          (Value::String(table), id) => RecordId::new(table, id.to_string()),
+          (table, id) => return Value::Array(vec![table.clone(), Value::String(id.to_string())]),
+        };
+
+        Value::String(id.to_string())
+      }
+      inner => cbor_to_json(inner),
+    },
+    // binary uuid
+    CborValue::Tag(37, inner) => match *inner {
+      CborValue::Bytes(bytes) if bytes.len() == 16 => {
+        let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+
+        Value::String(format!(
+          "{}-{}-{}-{}-{}",
+          &hex[0..8],
+          &hex[8..12],
+          &hex[12..16],
+          &hex[16..20],
+          &hex[20..32]
+        ))
+      }
+      inner => cbor_to_json(inner),
+    },
+    // compact datetime, as `[seconds, nanoseconds]` since the unix epoch
+    CborValue::Tag(12, inner) => match compact_parts(&inner) {
+      Some((seconds, nanoseconds)) => Value::String(datetime_string(seconds, nanoseconds)),
+      None => cbor_to_json(*inner),
+    },
+    // compact duration, as `[seconds, nanoseconds]`
+    CborValue::Tag(14, inner) => match compact_parts(&inner) {
+      Some((seconds, nanoseconds)) if seconds >= 0 => {
+        Value::String(duration_string(seconds as u64, nanoseconds))
+      }
+      _ => cbor_to_json(*inner),
+    },
+    CborValue::Tag(_, inner) => cbor_to_json(*inner),
+    _ => Value::Null,
+  }
+}
+
+/// Read the `[seconds, nanoseconds]` array of the compact datetimes and
+/// durations, where both parts are optional and default to zero.
+fn compact_parts(value: &CborValue) -> Option<(i64, u32)> {
+  let CborValue::Array(parts) = value else {
+    return None;
+  };
+
+  let part = |index: usize| match parts.get(index) {
+    None => Some(0),
+    Some(CborValue::Integer(integer)) => Some(i128::from(*integer)),
+    Some(_) => None,
+  };
+
+  if parts.len() > 2 {
+    return None;
+  }
+
+  let seconds = i64::try_from(part(0)?).ok()?;
+  let nanoseconds = u32::try_from(part(1)?)
+    .ok()
+    .filter(|nanoseconds| *nanoseconds < 1_000_000_000)?;
+
+  Some((seconds, nanoseconds))
+}
+
+/// Format a datetime in RFC 3339 in UTC, with 0, 3, 6 or 9 digits for the
+/// fraction of the second like SurrealDB does.
+fn datetime_string(seconds: i64, nanoseconds: u32) -> String {
+  let days = seconds.div_euclid(86_400);
+  let time = seconds.rem_euclid(86_400);
+
+  // the days since the epoch into a date of the proleptic gregorian calendar,
+  // from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let day_of_era = z.rem_euclid(146_097);
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let shifted_month = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+  let month = match shifted_month < 10 {
+    true => shifted_month + 3,
+    false => shifted_month - 9,
+  };
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+  let fraction = match nanoseconds {
+    0 => String::new(),
+    n if n % 1_000_000 == 0 => format!(".{:03}", n / 1_000_000),
+    n if n % 1_000 == 0 => format!(".{:06}", n / 1_000),
+    n => format!(".{n:09}"),
+  };
+
+  format!(
+    "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}{fraction}Z",
+    time / 3600,
+    time % 3600 / 60,
+    time % 60
+  )
+}
+
+/// Format a duration the way SurrealDB displays them, from the years down to
+/// the nanoseconds and leaving out the units that are zero: `1h30m`, `0ns`.
+fn duration_string(seconds: u64, nanoseconds: u32) -> String {
+  if seconds == 0 && nanoseconds == 0 {
+    return "0ns".to_owned();
+  }
+
+  let units = [
+    (seconds / 31_536_000, "y"),
+    (seconds % 31_536_000 / 604_800, "w"),
+    (seconds % 31_536_000 % 604_800 / 86_400, "d"),
+    (seconds % 86_400 / 3600, "h"),
+    (seconds % 3600 / 60, "m"),
+    (seconds % 60, "s"),
+    (u64::from(nanoseconds / 1_000_000), "ms"),
+    (u64::from(nanoseconds % 1_000_000 / 1_000), "µs"),
+    (u64::from(nanoseconds % 1_000), "ns"),
+  ];
+
+  units
+    .iter()
+    .filter(|(amount, _)| *amount > 0)
+    .map(|(amount, unit)| format!("{amount}{unit}"))
+    .collect()
+}
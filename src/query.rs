@@ -0,0 +1,233 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::retry;
+use crate::rpc::RpcChannelError;
+use crate::rpc::RpcResult;
+use crate::syntax;
+use crate::SurrealClient;
+use crate::SurrealResponseData;
+
+/// A `(name, value)` pair to bind to the `$name` parameter of a [Query], it
+/// is obtained from any tuple whose value implements [Serialize].
+pub struct Binding {
+  name: String,
+  value: Result<Value, serde_json::Error>,
+}
+
+impl<K: Into<String>, V: Serialize> From<(K, V)> for Binding {
+  fn from((name, value): (K, V)) -> Self {
+    Self {
+      name: name.into(),
+      value: serde_json::to_value(value),
+    }
+  }
+}
+
+/// A query that is being built before being sent to the database, obtained
+/// through [`SurrealClient::query()`].
+///
+/// Parameters are bound with any type that implements [Serialize], and every
+/// `$param` found in the query text must have a binding before the query is
+/// sent:
+/// ```rust,ignore
+/// let user: Option<User> = client
+///   .query("select * from User where name = $name and age > $age")
+///   .bind(("name", &name))
+///   .bind(("age", 18))
+///   .find_one()
+///   .await?;
+/// ```
+pub struct Query<'a> {
+  client: &'a mut SurrealClient,
+  text: String,
+  bindings: Map<String, Value>,
+
+  /// The first error encountered while binding a value, it is kept until the
+  /// query is sent so the builder methods can remain chainable.
+  error: Option<RpcChannelError>,
+
+  /// Whether the query can be retried even if it isn't read-only.
+  idempotent: bool,
+
+  /// The namespace and database the session is switched to before the query
+  /// is sent, for the queries of a [ScopedClient](crate::ScopedClient).
+  scope: Option<(String, String)>,
+}
+
+impl<'a> Query<'a> {
+  pub(crate) fn new(client: &'a mut SurrealClient, text: String) -> Self {
+    Self {
+      client,
+      text,
+      bindings: Map::new(),
+      error: None,
+      idempotent: false,
+      scope: None,
+    }
+  }
+
+  pub(crate) fn scoped(mut self, namespace: String, database: String) -> Self {
+    self.scope = Some((namespace, database));
+    self
+  }
+
+  /// Bind the `value` of a `(name, value)` pair to the `$name` parameter of
+  /// the query.
+  pub fn bind<B: Into<Binding>>(mut self, binding: B) -> Self {
+    let Binding { name, value } = binding.into();
+
+    match value {
+      Ok(value) => {
+        self.bindings.insert(name, value);
+      }
+      Err(inner) => self.set_error(inner.into()),
+    };
+
+    self
+  }
+
+  /// Bind every field of the supplied object, each field becomes a parameter
+  /// with the same name. The `object` must serialize into a JSON object.
+  pub fn bind_object<T: Serialize>(mut self, object: T) -> Self {
+    match serde_json::to_value(object) {
+      Ok(Value::Object(fields)) => self.bindings.extend(fields),
+      Ok(_) => self.set_error(RpcChannelError::InvalidBinding {
+        message: "bind_object expects a value that serializes into an object".to_owned(),
+      }),
+      Err(inner) => self.set_error(inner.into()),
+    };
+
+    self
+  }
+
+  /// Mark the query as safe to be sent more than once, so it is retried
+  /// according to the client's [RetryPolicy](crate::RetryPolicy) even though
+  /// it isn't read-only.
+  pub fn idempotent(mut self) -> Self {
+    self.idempotent = true;
+    self
+  }
+
+  /// The query text as it will be sent to the database.
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+
+  /// The parameters bound so far.
+  pub fn bindings(&self) -> &Map<String, Value> {
+    &self.bindings
+  }
+
+  /// Get the names of the `$params` used in the query that have no binding,
+  /// the [session variables](SurrealClient::session_vars) count as bindings.
+  pub fn missing_bindings(&self) -> Vec<String> {
+    let mut missing: Vec<String> = Vec::new();
+    let session_vars = self.client.session_vars();
+
+    for name in syntax::parameters(&self.text) {
+      let is_bound = self.bindings.contains_key(&name) || session_vars.contains_key(&name);
+
+      if !is_bound && !missing.contains(&name) {
+        missing.push(name);
+      }
+    }
+
+    missing
+  }
+
+  fn set_error(&mut self, error: RpcChannelError) {
+    if self.error.is_none() {
+      self.error = Some(error);
+    }
+  }
+
+  /// Confirm the bindings are valid and that every parameter has a binding,
+  /// then switch the session to the scope of the query if it isn't already.
+  async fn prepare(&mut self) -> RpcResult<()> {
+    if let Some(error) = self.error.take() {
+      return Err(error);
+    }
+
+    if let Some(name) = self.missing_bindings().into_iter().next() {
+      return Err(RpcChannelError::MissingBinding { name });
+    }
+
+    if let Some((namespace, database)) = &self.scope {
+      if self.client.session_scope() != Some((namespace.as_str(), database.as_str())) {
+        self
+          .client
+          .use_namespace(namespace.as_str(), database.as_str())
+          .await?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Send the query and return the raw response.
+  pub async fn send(mut self) -> RpcResult<SurrealResponseData> {
+    self.prepare().await?;
+
+    let response = self
+      .client
+      .send_query(self.text, Value::Object(self.bindings))
+      .await?
+      .await?;
+
+    Ok(response)
+  }
+
+  /// Send the query and wait for its response, any statement of the query that
+  /// failed is turned into an error.
+  async fn execute(mut self) -> RpcResult<SurrealResponseData> {
+    self.prepare().await?;
+
+    let retryable = self.idempotent || retry::is_read_only(&self.text);
+
+    self
+      .client
+      .send_retryable_query(self.text, Value::Object(self.bindings), retryable)
+      .await
+  }
+
+  /// Send the query then return the **first** [T] returned by the first
+  /// statement.
+  pub async fn find_one<T: DeserializeOwned>(self) -> RpcResult<Option<T>> {
+    let response = self.execute().await?;
+    let value = response
+      .get_nth_query_result(0)
+      .and_then(|query_results| query_results.results().first().cloned());
+
+    match value {
+      None => Ok(None),
+      Some(inner) => {
+        let deser_result = serde_json::from_value::<T>(inner)?;
+
+        Ok(Some(deser_result))
+      }
+    }
+  }
+
+  /// Send the query then return every [T] returned by the first statement.
+  pub async fn find_many<T: DeserializeOwned>(self) -> RpcResult<Vec<T>> {
+    self.take(0).await
+  }
+
+  /// Send the query then return every [T] returned by the `n`-th statement of
+  /// the query. This is useful for queries made of multiple statements where
+  /// the interesting rows are not returned by the first one.
+  pub async fn take<T: DeserializeOwned>(self, n: usize) -> RpcResult<Vec<T>> {
+    let response = self.execute().await?;
+    let values = response
+      .get_nth_query_result(n)
+      .map(|query_results| query_results.results().clone())
+      .unwrap_or_default();
+
+    let deser_result: Vec<T> = serde_json::from_value(Value::Array(values))?;
+
+    Ok(deser_result)
+  }
+}
@@ -0,0 +1,154 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use serde_json::Value;
+
+use crate::errors::ParseRecordIdError;
+
+/// The id of a record, made of the name of its table and of the id of the
+/// record in that table: `table:id`.
+///
+/// It is (de)serialized from/to its `table:id` string form, and the ids that
+/// aren't plain identifiers are escaped as `table:⟨id⟩` when displayed so the
+/// [RecordId] can be written directly in a query. A numeric id like in
+/// `table:1` is a different id than the `table:⟨1⟩` string id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RecordId {
+  table: String,
+  id: String,
+  numeric: bool,
+}
+
+impl RecordId {
+  /// A record id whose `id` is a string, even if it only contains digits.
+  pub fn new<T: Into<String>, I: Into<String>>(table: T, id: I) -> Self {
+    Self {
+      table: table.into(),
+      id: id.into(),
+      numeric: false,
+    }
+  }
+
+  pub fn numeric<T: Into<String>>(table: T, id: i64) -> Self {
+    Self {
+      table: table.into(),
+      id: id.to_string(),
+      numeric: true,
+    }
+  }
+
+  pub fn table(&self) -> &str {
+    &self.table
+  }
+
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  pub fn is_numeric(&self) -> bool {
+    self.numeric
+  }
+
+  /// The id as it is bound to the `$id` of a `type::thing($table, $id)`.
+  pub(crate) fn id_param(&self) -> Value {
+    match self.id.parse::<i64>() {
+      Ok(id) if self.numeric => Value::from(id),
+      _ => Value::from(self.id.as_str()),
+    }
+  }
+}
+
+/// Whether `text` can be written in a query without being escaped.
+pub(crate) fn is_plain_identifier(text: &str) -> bool {
+  !text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Write the name of a `table` so it can be used in a query, between backticks
+/// if it is not a plain identifier.
+pub(crate) fn table_identifier(table: &str) -> String {
+  match is_plain_identifier(table) {
+    true => table.to_owned(),
+    false => format!("`{}`", escape(table, '`')),
+  }
+}
+
+impl Display for RecordId {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}:", table_identifier(&self.table))?;
+
+    // a string id made of digits only would be read as a number
+    let is_number = self.id.chars().all(|c| c.is_ascii_digit());
+
+    match self.numeric || (is_plain_identifier(&self.id) && !is_number) {
+      true => write!(f, "{}", self.id),
+      false => write!(f, "⟨{}⟩", escape(&self.id, '⟩')),
+    }
+  }
+}
+
+impl FromStr for RecordId {
+  type Err = ParseRecordIdError;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let error = || ParseRecordIdError {
+      value: value.to_owned(),
+    };
+
+    let (table, id) = value.split_once(':').ok_or_else(error)?;
+    let table = unescape(table, '`', '`').unwrap_or_else(|| table.to_owned());
+
+    let (id, numeric) = match unescape(id, '⟨', '⟩') {
+      Some(id) => (id, false),
+      None => (id.to_owned(), id.parse::<i64>().is_ok()),
+    };
+
+    if table.is_empty() || id.is_empty() {
+      return Err(error());
+    }
+
+    Ok(Self { table, id, numeric })
+  }
+}
+
+/// Escape the `\` and the `close` delimiter of a text written between
+/// delimiters.
+fn escape(text: &str, close: char) -> String {
+  text
+    .replace('\\', "\\\\")
+    .replace(close, &format!("\\{close}"))
+}
+
+/// Get the text between the `open` and `close` delimiters without its
+/// escapes, or [None] if it is not delimited.
+fn unescape(text: &str, open: char, close: char) -> Option<String> {
+  let inner = text.strip_prefix(open)?.strip_suffix(close)?;
+  let mut unescaped = String::with_capacity(inner.len());
+  let mut chars = inner.chars();
+
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => unescaped.extend(chars.next()),
+      c => unescaped.push(c),
+    };
+  }
+
+  Some(unescaped)
+}
+
+impl Serialize for RecordId {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+impl<'de> Deserialize<'de> for RecordId {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let value = String::deserialize(deserializer)?;
+
+    value.parse().map_err(serde::de::Error::custom)
+  }
+}
@@ -2,6 +2,10 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_json::Value;
 
+use crate::errors::StatementError;
+use crate::rpc::RpcChannelError;
+use crate::rpc::RpcResult;
+
 /// A raw, unparsed, response from the SurrealDB instance.
 ///
 /// A surreal response always looks like the following:
@@ -48,6 +52,22 @@ impl SurrealResponseData {
       _ => None,
     }
   }
+
+  /// Return an error built from the first statement of the response that
+  /// failed, if any.
+  pub fn check_statements(&self) -> RpcResult<()> {
+    let failed_statement = match &self.result {
+      SurrealResponseResult::Data(results) => results.iter().find_map(|result| result.error()),
+      _ => None,
+    };
+
+    match failed_statement {
+      Some(message) => Err(RpcChannelError::SurrealStatementError {
+        inner: StatementError::from_message(message),
+      }),
+      None => Ok(()),
+    }
+  }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -83,11 +103,53 @@ pub enum SurrealResponseResult {
 /// ```
 ///
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(from = "RawQueryResult")]
 pub struct SurrealQueryResult {
   pub time: String,
   pub status: String,
 
   result: Vec<Value>,
+
+  /// The error message of the statement when its status is not `OK`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  detail: Option<String>,
+}
+
+/// The statement result as it is sent by SurrealDB. Depending on the version
+/// a failed statement holds its error message in `detail` or in `result`.
+#[derive(Deserialize)]
+struct RawQueryResult {
+  time: String,
+  status: String,
+
+  #[serde(default)]
+  result: Value,
+
+  #[serde(default)]
+  detail: Option<String>,
+}
+
+impl From<RawQueryResult> for SurrealQueryResult {
+  fn from(raw: RawQueryResult) -> Self {
+    let mut detail = raw.detail;
+    let result = match raw.result {
+      Value::Array(rows) => rows,
+      Value::Null => Vec::new(),
+      Value::String(message) if raw.status != "OK" => {
+        detail.get_or_insert(message);
+
+        Vec::new()
+      }
+      row => vec![row],
+    };
+
+    Self {
+      time: raw.time,
+      status: raw.status,
+      result,
+      detail,
+    }
+  }
 }
 
 impl SurrealQueryResult {
@@ -95,6 +157,19 @@ impl SurrealQueryResult {
     &self.result
   }
 
+  /// Whether the statement was successfully executed
+  pub fn is_ok(&self) -> bool {
+    self.status == "OK"
+  }
+
+  /// Get the error message of the statement if it failed
+  pub fn error(&self) -> Option<&str> {
+    match self.is_ok() {
+      true => None,
+      false => Some(self.detail.as_deref().unwrap_or(&self.status)),
+    }
+  }
+
   /// Get the inner results and extract the
   /// [Value] out of the `key` for each row.
   ///
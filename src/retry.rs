@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use crate::syntax;
+use crate::syntax::TokenKind;
+
+/// Describes how the queries that are safe to repeat are retried after a
+/// transient failure, refer to [`crate::rpc::RpcChannelError::is_transient()`].
+///
+/// Only the read-only queries, whose statements are all `SELECT` or `INFO`
+/// statements, and the queries marked as [idempotent](crate::Query::idempotent)
+/// are retried. The queries are not retried once the connection of the client
+/// is closed.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+  /// The maximum amount of retries after the first attempt.
+  pub max_retries: u32,
+
+  /// The delay before the first retry, it is multiplied by the `multiplier`
+  /// after every retry.
+  pub initial_backoff: Duration,
+  pub max_backoff: Duration,
+  pub multiplier: u32,
+
+  /// How long to wait for the response of an attempt before it is considered
+  /// as failed. There is no limit if [None].
+  pub timeout: Option<Duration>,
+}
+
+impl RetryPolicy {
+  /// A policy that never retries and never times out, which is the policy of
+  /// new clients.
+  pub fn none() -> Self {
+    Self {
+      max_retries: 0,
+      initial_backoff: Duration::ZERO,
+      max_backoff: Duration::ZERO,
+      multiplier: 1,
+      timeout: None,
+    }
+  }
+
+  /// Retry up to `max_retries` times, starting with a 50ms delay that doubles
+  /// after every retry without exceeding 2 seconds.
+  pub fn exponential(max_retries: u32) -> Self {
+    Self {
+      max_retries,
+      initial_backoff: Duration::from_millis(50),
+      max_backoff: Duration::from_secs(2),
+      multiplier: 2,
+      timeout: None,
+    }
+  }
+
+  pub fn with_timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+    self
+  }
+
+  /// The delay before the `retry`-th retry, starting at 0.
+  pub fn backoff(&self, retry: u32) -> Duration {
+    let factor = self.multiplier.saturating_pow(retry);
+
+    self
+      .initial_backoff
+      .saturating_mul(factor)
+      .min(self.max_backoff)
+  }
+}
+
+impl Default for RetryPolicy {
+  fn default() -> Self {
+    Self::none()
+  }
+}
+
+/// The keywords of the statements that write data, a read-only query may not
+/// contain them even in a subquery.
+const WRITE_KEYWORDS: [&str; 8] = [
+  "create", "update", "upsert", "delete", "relate", "insert", "define", "remove",
+];
+
+/// Whether every statement of the `query` only reads data, in which case the
+/// query can safely be sent more than once.
+///
+/// The statements must be `SELECT` or `INFO` statements that contain neither
+/// a nested write statement nor a call to a custom `fn::` function, whose body
+/// may write data.
+pub(crate) fn is_read_only(query: &str) -> bool {
+  syntax::split_statements(query)
+    .into_iter()
+    .all(|statement| {
+      let (tokens, error) = syntax::tokenize(statement);
+      let words: Vec<Option<String>> = tokens
+        .iter()
+        .map(|token| match &token.kind {
+          TokenKind::Word(word) => Some(word.to_lowercase()),
+          _ => None,
+        })
+        .collect();
+
+      let reads = matches!(
+        words.first(),
+        Some(Some(keyword)) if keyword == "select" || keyword == "info"
+      );
+      let writes = words
+        .iter()
+        .flatten()
+        .any(|word| WRITE_KEYWORDS.contains(&word.as_str()));
+      let calls_custom_function = tokens.windows(3).any(|window| {
+        matches!(
+          [&window[0].kind, &window[1].kind, &window[2].kind],
+          [TokenKind::Word(word), TokenKind::Symbol(':'), TokenKind::Symbol(':')]
+            if word.eq_ignore_ascii_case("fn")
+        )
+      });
+
+      error.is_none() && reads && !writes && !calls_custom_function
+    })
+}
@@ -1,24 +1,84 @@
 use std::fmt::Display;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 
+use serde_json::json;
+use serde_json::Value;
+use thiserror::Error;
 use tokio::sync::oneshot;
 use tokio_tungstenite::tungstenite;
-use thiserror::Error;
 
+use crate::errors::StatementError;
 use crate::errors::SurrealError;
+use crate::errors::SyntaxError;
+use crate::version::MIN_SERVER_VERSION;
+use crate::version::UNSUPPORTED_SERVER_MAJOR;
 
 pub type RpcResult<T> = Result<T, RpcChannelError>;
 
+static EXPOSE_ERROR_DETAILS: AtomicBool = AtomicBool::new(false);
+
+/// Set whether the [problem details](RpcChannelError::problem_details) of the
+/// errors include their full message, which may contain parts of the queries
+/// and of the data. The details are hidden by default and should remain hidden
+/// in production.
+pub fn expose_error_details(expose: bool) {
+  EXPOSE_ERROR_DETAILS.store(expose, Ordering::Relaxed);
+}
+
 #[derive(Debug, Error)]
 pub enum RpcChannelError {
-  SurrealBodyParsingError { inner: serde_json::Error },
-  SocketError { inner: tungstenite::Error },
-  SurrealQueryError { inner: SurrealError },
-  OneshotError { inner: oneshot::error::RecvError },
+  SurrealBodyParsingError {
+    inner: serde_json::Error,
+  },
+  SocketError {
+    inner: Box<tungstenite::Error>,
+  },
+  SurrealQueryError {
+    inner: SurrealError,
+  },
+  SurrealStatementError {
+    inner: StatementError,
+  },
+  OneshotError {
+    inner: oneshot::error::RecvError,
+  },
+  MissingBinding {
+    name: String,
+  },
+  InvalidBinding {
+    message: String,
+  },
+  Timeout {
+    duration: Duration,
+  },
+  SyntaxError {
+    inner: SyntaxError,
+  },
+  CborError {
+    message: String,
+  },
+  UnsupportedServer {
+    version: String,
+  },
+
+  /// The client already has `max_in_flight` requests awaiting their response,
+  /// refer to [FlowControl](crate::FlowControl).
+  Overloaded {
+    max_in_flight: usize,
+  },
+
+  /// The connection was closed, the client can't send any request anymore and
+  /// must be replaced by a new one.
+  ClientClosed,
 }
 
 impl From<tungstenite::Error> for RpcChannelError {
   fn from(inner: tungstenite::Error) -> Self {
-    Self::SocketError { inner }
+    Self::SocketError {
+      inner: Box::new(inner),
+    }
   }
 }
 
@@ -44,27 +104,179 @@ impl Display for RpcChannelError {
       RpcChannelError::SurrealQueryError { inner } => {
         write!(f, "Surreal query errror: {:?}", inner)
       }
+      RpcChannelError::SurrealStatementError { inner } => {
+        write!(f, "Surreal statement error: {inner}")
+      }
       RpcChannelError::OneshotError { inner } => {
         write!(f, "Oneshot receiver error: {inner}")
       }
+      RpcChannelError::MissingBinding { name } => {
+        write!(f, "Missing binding for the query parameter ${name}")
+      }
+      RpcChannelError::InvalidBinding { message } => write!(f, "Invalid binding: {message}"),
+      RpcChannelError::Timeout { duration } => {
+        write!(f, "No response from the database after {duration:?}")
+      }
+      RpcChannelError::SyntaxError { inner } => write!(f, "Query syntax error: {inner}"),
+      RpcChannelError::CborError { message } => write!(f, "CBOR codec error: {message}"),
+      RpcChannelError::UnsupportedServer { version } => write!(
+        f,
+        "Unsupported SurrealDB version {version}, the client supports versions {} to {}.x",
+        MIN_SERVER_VERSION,
+        UNSUPPORTED_SERVER_MAJOR - 1
+      ),
+      RpcChannelError::Overloaded { max_in_flight } => write!(
+        f,
+        "Too many requests in flight, the client is limited to {max_in_flight}"
+      ),
+      RpcChannelError::ClientClosed => write!(f, "The connection of the client is closed"),
+    }
+  }
+}
+
+impl RpcChannelError {
+  /// The HTTP status code that best describes the error, for the web framework
+  /// integrations.
+  ///
+  /// Failures to reach the database are `503`, and the errors reported by the
+  /// database depend on their [StatementError]: unique index violations and
+  /// existing records are `409`, missing records are `404`, permission errors
+  /// are `403` and transaction conflicts are `503`. Everything else is a `500`.
+  pub fn http_status_code(&self) -> u16 {
+    match self {
+      RpcChannelError::SocketError { inner: _ } | RpcChannelError::OneshotError { inner: _ } => 503,
+      RpcChannelError::Timeout { duration: _ } => 504,
+      RpcChannelError::Overloaded { max_in_flight: _ } | RpcChannelError::ClientClosed => 503,
+      _ => match self.statement_error() {
+        Some(StatementError::UniqueViolation { .. })
+        | Some(StatementError::RecordAlreadyExists { .. }) => 409,
+        Some(StatementError::RecordNotFound { .. }) => 404,
+        Some(StatementError::PermissionDenied { .. }) => 403,
+        Some(StatementError::TransactionConflict { .. }) => 503,
+        _ => 500,
+      },
+    }
+  }
+
+  /// Get the [StatementError] out of the errors reported by the database, the
+  /// message of the RPC errors is parsed as well.
+  pub fn statement_error(&self) -> Option<StatementError> {
+    match self {
+      RpcChannelError::SurrealStatementError { inner } => Some(inner.clone()),
+      RpcChannelError::SurrealQueryError { inner } => {
+        Some(StatementError::from_message(inner.message()))
+      }
+      _ => None,
+    }
+  }
+
+  /// A JSON [problem details](https://www.rfc-editor.org/rfc/rfc7807) object
+  /// describing the error. The `detail` member holds the full message of the
+  /// error only if it was enabled with [expose_error_details], it holds the
+  /// [public message](Self::public_message) otherwise.
+  pub fn problem_details(&self) -> Value {
+    let mut details = json!({
+      "type": "about:blank",
+      "title": self.public_message(),
+      "status": self.http_status_code(),
+      "detail": self.public_message(),
+    });
+
+    if EXPOSE_ERROR_DETAILS.load(Ordering::Relaxed) {
+      details["detail"] = Value::from(self.to_string());
+
+      if let RpcChannelError::SurrealQueryError { inner } = self {
+        details["code"] = Value::from(inner.code());
+      }
+    }
+
+    details
+  }
+
+  /// A short description of the error that doesn't leak any detail about the
+  /// query or the database, and is therefore safe to send to clients.
+  pub fn public_message(&self) -> &'static str {
+    match self {
+      RpcChannelError::SurrealBodyParsingError { inner: _ } => {
+        "Failed to parse results from the database"
+      }
+      RpcChannelError::SocketError { inner: _ } => "RPC socket failure",
+      RpcChannelError::SurrealQueryError { inner: _ } => "Incorrect query was sent to the database",
+      RpcChannelError::SurrealStatementError { inner: _ } => {
+        "A statement of the query failed to execute"
+      }
+      RpcChannelError::OneshotError { inner: _ } => "SPSC channel failure",
+      RpcChannelError::MissingBinding { name: _ }
+      | RpcChannelError::InvalidBinding { message: _ } => {
+        "Incorrect query parameters were supplied"
+      }
+      RpcChannelError::Timeout { duration: _ } => "The database took too long to respond",
+      RpcChannelError::SyntaxError { inner: _ } => "Incorrect query was sent to the database",
+      RpcChannelError::CborError { message: _ } => "Failed to encode or decode a CBOR message",
+      RpcChannelError::UnsupportedServer { version: _ } => "Unsupported database version",
+      RpcChannelError::Overloaded { max_in_flight: _ } => "Too many requests to the database",
+      RpcChannelError::ClientClosed => "The connection to the database is closed",
+    }
+  }
+
+  /// Attach the snippet of the `query` to the parse errors reported by the
+  /// database, so they show where the query failed to parse.
+  pub(crate) fn with_query_snippet(self, query: &str) -> Self {
+    match self {
+      RpcChannelError::SurrealStatementError { inner } => RpcChannelError::SurrealStatementError {
+        inner: inner.with_query_snippet(query),
+      },
+      RpcChannelError::SurrealQueryError { inner } => {
+        match StatementError::from_message(inner.message()) {
+          parse @ StatementError::Parse { .. } => RpcChannelError::SurrealStatementError {
+            inner: parse.with_query_snippet(query),
+          },
+          _ => RpcChannelError::SurrealQueryError { inner },
+        }
+      }
+      error => error,
+    }
+  }
+
+  /// Whether the error is likely caused by a temporary condition, in which
+  /// case sending the same request again may succeed.
+  ///
+  /// Socket failures, timeouts and overloads are transient, and so are the
+  /// errors from the database that report a conflict between transactions or a
+  /// busy resource. Errors caused by the request itself, like parse errors or
+  /// invalid parameters, are never transient.
+  pub fn is_transient(&self) -> bool {
+    match self {
+      RpcChannelError::SocketError { inner: _ }
+      | RpcChannelError::OneshotError { inner: _ }
+      | RpcChannelError::Timeout { duration: _ }
+      | RpcChannelError::Overloaded { max_in_flight: _ } => true,
+      RpcChannelError::SurrealQueryError { inner } => match inner.code() {
+        // parse error, invalid request, method not found, invalid params
+        -32700 | -32600 | -32601 | -32602 => false,
+        _ => matches!(
+          self.statement_error(),
+          Some(StatementError::TransactionConflict { .. })
+        ),
+      },
+      RpcChannelError::SurrealStatementError { inner } => {
+        matches!(inner, StatementError::TransactionConflict { .. })
+      }
+      _ => false,
     }
   }
 }
 
 #[cfg(feature = "actix")]
 impl actix_web::ResponseError for RpcChannelError {
+  fn status_code(&self) -> actix_web::http::StatusCode {
+    actix_web::http::StatusCode::from_u16(self.http_status_code())
+      .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR)
+  }
+
   fn error_response(&self) -> actix_web::HttpResponse<actix_web::body::BoxBody> {
     actix_web::HttpResponse::build(self.status_code())
-      .insert_header(actix_web::http::header::ContentType::html())
-      .body(match self {
-        RpcChannelError::SurrealBodyParsingError { inner: _ } => {
-          "Failed to parse results from the database"
-        }
-        RpcChannelError::SocketError { inner: _ } => "RPC socket failure",
-        RpcChannelError::SurrealQueryError { inner: _ } => {
-          "Incorrect query was sent to the database"
-        }
-        RpcChannelError::OneshotError { inner: _ } => "SPSC channel failure",
-      })
+      .content_type("application/problem+json")
+      .body(self.problem_details().to_string())
   }
 }
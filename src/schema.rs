@@ -0,0 +1,1000 @@
+//! Typed `DEFINE` statements that render to SurrealQL, and the tools to apply
+//! them through a [SurrealClient] or compare them with the schema currently
+//! defined in the database.
+//!
+//! ```rust,ignore
+//! let schema = Schema::new()
+//!   .define(DefineTable::new("account").schemafull())
+//!   .define(
+//!     DefineField::new("email", "account")
+//!       .kind("string")
+//!       .assert("string::is::email($value)"),
+//!   )
+//!   .define(DefineIndex::new("account_email", "account", ["email"]).unique());
+//!
+//! let diff = schema.diff(&mut client).await?;
+//!
+//! if !diff.is_empty() {
+//!   schema.apply(&mut client).await?;
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use serde_json::json;
+use serde_json::Value;
+
+use crate::record_id::table_identifier;
+use crate::rpc::RpcResult;
+use crate::syntax;
+use crate::syntax::TokenKind;
+use crate::SurrealClient;
+
+/// The operations a permission clause applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionAction {
+  Select,
+  Create,
+  Update,
+  Delete,
+}
+
+impl Display for PermissionAction {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      PermissionAction::Select => write!(f, "select"),
+      PermissionAction::Create => write!(f, "create"),
+      PermissionAction::Update => write!(f, "update"),
+      PermissionAction::Delete => write!(f, "delete"),
+    }
+  }
+}
+
+/// The `PERMISSIONS` clause of a table or field definition.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Permissions {
+  None,
+  Full,
+
+  /// One `FOR <action> <rule>` clause per entry, where the rule is either
+  /// `NONE`, `FULL` or a `WHERE <condition>` clause.
+  For(Vec<(PermissionAction, String)>),
+}
+
+impl Display for Permissions {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Permissions::None => write!(f, "PERMISSIONS NONE"),
+      Permissions::Full => write!(f, "PERMISSIONS FULL"),
+      Permissions::For(rules) => {
+        write!(f, "PERMISSIONS")?;
+
+        for (action, rule) in rules {
+          write!(f, " FOR {action} {rule}")?;
+        }
+
+        Ok(())
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineNamespace {
+  pub name: String,
+}
+
+impl DefineNamespace {
+  pub fn new<N: Into<String>>(name: N) -> Self {
+    Self { name: name.into() }
+  }
+}
+
+impl Display for DefineNamespace {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "DEFINE NAMESPACE {}", self.name)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineDatabase {
+  pub name: String,
+}
+
+impl DefineDatabase {
+  pub fn new<N: Into<String>>(name: N) -> Self {
+    Self { name: name.into() }
+  }
+}
+
+impl Display for DefineDatabase {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "DEFINE DATABASE {}", self.name)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineTable {
+  pub name: String,
+  pub drop: bool,
+  pub schemafull: bool,
+  pub permissions: Option<Permissions>,
+}
+
+impl DefineTable {
+  pub fn new<N: Into<String>>(name: N) -> Self {
+    Self {
+      name: name.into(),
+      drop: false,
+      schemafull: false,
+      permissions: None,
+    }
+  }
+
+  pub fn drop(mut self) -> Self {
+    self.drop = true;
+    self
+  }
+
+  pub fn schemafull(mut self) -> Self {
+    self.schemafull = true;
+    self
+  }
+
+  pub fn permissions(mut self, permissions: Permissions) -> Self {
+    self.permissions = Some(permissions);
+    self
+  }
+}
+
+impl Display for DefineTable {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "DEFINE TABLE {}", table_identifier(&self.name))?;
+
+    if self.drop {
+      write!(f, " DROP")?;
+    }
+
+    match self.schemafull {
+      true => write!(f, " SCHEMAFULL")?,
+      false => write!(f, " SCHEMALESS")?,
+    };
+
+    if let Some(permissions) = &self.permissions {
+      write!(f, " {permissions}")?;
+    }
+
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineField {
+  pub name: String,
+  pub table: String,
+  pub kind: Option<String>,
+  pub value: Option<String>,
+  pub default: Option<String>,
+  pub assert: Option<String>,
+  pub permissions: Option<Permissions>,
+}
+
+impl DefineField {
+  pub fn new<N: Into<String>, T: Into<String>>(name: N, table: T) -> Self {
+    Self {
+      name: name.into(),
+      table: table.into(),
+      kind: None,
+      value: None,
+      default: None,
+      assert: None,
+      permissions: None,
+    }
+  }
+
+  /// The `TYPE` of the field, for example `string` or `record(account)`.
+  pub fn kind<K: Into<String>>(mut self, kind: K) -> Self {
+    self.kind = Some(kind.into());
+    self
+  }
+
+  pub fn value<V: Into<String>>(mut self, value: V) -> Self {
+    self.value = Some(value.into());
+    self
+  }
+
+  pub fn default<V: Into<String>>(mut self, default: V) -> Self {
+    self.default = Some(default.into());
+    self
+  }
+
+  pub fn assert<A: Into<String>>(mut self, assert: A) -> Self {
+    self.assert = Some(assert.into());
+    self
+  }
+
+  pub fn permissions(mut self, permissions: Permissions) -> Self {
+    self.permissions = Some(permissions);
+    self
+  }
+}
+
+impl Display for DefineField {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "DEFINE FIELD {} ON {}",
+      self.name,
+      table_identifier(&self.table)
+    )?;
+
+    if let Some(kind) = &self.kind {
+      write!(f, " TYPE {kind}")?;
+    }
+
+    if let Some(default) = &self.default {
+      write!(f, " DEFAULT {default}")?;
+    }
+
+    if let Some(value) = &self.value {
+      write!(f, " VALUE {value}")?;
+    }
+
+    if let Some(assert) = &self.assert {
+      write!(f, " ASSERT {assert}")?;
+    }
+
+    if let Some(permissions) = &self.permissions {
+      write!(f, " {permissions}")?;
+    }
+
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineIndex {
+  pub name: String,
+  pub table: String,
+  pub fields: Vec<String>,
+  pub unique: bool,
+}
+
+impl DefineIndex {
+  pub fn new<N, T, F, I>(name: N, table: T, fields: I) -> Self
+  where
+    N: Into<String>,
+    T: Into<String>,
+    F: Into<String>,
+    I: IntoIterator<Item = F>,
+  {
+    Self {
+      name: name.into(),
+      table: table.into(),
+      fields: fields.into_iter().map(Into::into).collect(),
+      unique: false,
+    }
+  }
+
+  pub fn unique(mut self) -> Self {
+    self.unique = true;
+    self
+  }
+}
+
+impl Display for DefineIndex {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "DEFINE INDEX {} ON {} FIELDS {}",
+      self.name,
+      table_identifier(&self.table),
+      self.fields.join(", ")
+    )?;
+
+    if self.unique {
+      write!(f, " UNIQUE")?;
+    }
+
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineEvent {
+  pub name: String,
+  pub table: String,
+  pub when: String,
+  pub then: String,
+}
+
+impl DefineEvent {
+  pub fn new<N: Into<String>, T: Into<String>, E: Into<String>>(
+    name: N, table: T, then: E,
+  ) -> Self {
+    Self {
+      name: name.into(),
+      table: table.into(),
+      when: "true".to_owned(),
+      then: then.into(),
+    }
+  }
+
+  pub fn when<W: Into<String>>(mut self, when: W) -> Self {
+    self.when = when.into();
+    self
+  }
+}
+
+impl Display for DefineEvent {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "DEFINE EVENT {} ON TABLE {} WHEN {} THEN ({})",
+      self.name,
+      table_identifier(&self.table),
+      self.when,
+      self.then
+    )
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineScope {
+  pub name: String,
+  pub session: Option<String>,
+  pub signup: Option<String>,
+  pub signin: Option<String>,
+}
+
+impl DefineScope {
+  pub fn new<N: Into<String>>(name: N) -> Self {
+    Self {
+      name: name.into(),
+      session: None,
+      signup: None,
+      signin: None,
+    }
+  }
+
+  /// The duration of the sessions, for example `24h`.
+  pub fn session<S: Into<String>>(mut self, session: S) -> Self {
+    self.session = Some(session.into());
+    self
+  }
+
+  pub fn signup<S: Into<String>>(mut self, signup: S) -> Self {
+    self.signup = Some(signup.into());
+    self
+  }
+
+  pub fn signin<S: Into<String>>(mut self, signin: S) -> Self {
+    self.signin = Some(signin.into());
+    self
+  }
+}
+
+impl Display for DefineScope {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "DEFINE SCOPE {}", self.name)?;
+
+    if let Some(session) = &self.session {
+      write!(f, " SESSION {session}")?;
+    }
+
+    if let Some(signup) = &self.signup {
+      write!(f, " SIGNUP ({signup})")?;
+    }
+
+    if let Some(signin) = &self.signin {
+      write!(f, " SIGNIN ({signin})")?;
+    }
+
+    Ok(())
+  }
+}
+
+/// What a token definition applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenTarget {
+  Namespace,
+  Database,
+  Scope(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineToken {
+  pub name: String,
+  pub on: TokenTarget,
+
+  /// The signing algorithm, for example `HS512` or `EDDSA`.
+  pub algorithm: String,
+  pub value: String,
+}
+
+impl DefineToken {
+  pub fn new<N: Into<String>, A: Into<String>, V: Into<String>>(
+    name: N, on: TokenTarget, algorithm: A, value: V,
+  ) -> Self {
+    Self {
+      name: name.into(),
+      on,
+      algorithm: algorithm.into(),
+      value: value.into(),
+    }
+  }
+}
+
+impl Display for DefineToken {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "DEFINE TOKEN {} ON ", self.name)?;
+
+    match &self.on {
+      TokenTarget::Namespace => write!(f, "NAMESPACE")?,
+      TokenTarget::Database => write!(f, "DATABASE")?,
+      TokenTarget::Scope(scope) => write!(f, "SCOPE {scope}")?,
+    };
+
+    write!(
+      f,
+      " TYPE {} VALUE {}",
+      self.algorithm,
+      Value::from(self.value.as_str())
+    )
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineFunction {
+  /// The name of the function without its `fn::` prefix.
+  pub name: String,
+  pub args: Vec<(String, String)>,
+  pub body: String,
+}
+
+impl DefineFunction {
+  pub fn new<N: AsRef<str>>(name: N) -> Self {
+    let name = name.as_ref();
+
+    Self {
+      name: name.strip_prefix("fn::").unwrap_or(name).to_owned(),
+      args: Vec::new(),
+      body: String::new(),
+    }
+  }
+
+  /// Add an argument with the given `name`, without its `$`, and `kind`.
+  pub fn arg<N: Into<String>, K: Into<String>>(mut self, name: N, kind: K) -> Self {
+    self.args.push((name.into(), kind.into()));
+    self
+  }
+
+  pub fn body<B: Into<String>>(mut self, body: B) -> Self {
+    self.body = body.into();
+    self
+  }
+}
+
+impl Display for DefineFunction {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let args: Vec<String> = self
+      .args
+      .iter()
+      .map(|(name, kind)| format!("${name}: {kind}"))
+      .collect();
+
+    write!(
+      f,
+      "DEFINE FUNCTION fn::{}({}) {{ {} }}",
+      self.name,
+      args.join(", "),
+      self.body
+    )
+  }
+}
+
+/// Any of the `DEFINE` statements.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Definition {
+  Namespace(DefineNamespace),
+  Database(DefineDatabase),
+  Table(DefineTable),
+  Field(DefineField),
+  Index(DefineIndex),
+  Event(DefineEvent),
+  Scope(DefineScope),
+  Token(DefineToken),
+  Function(DefineFunction),
+}
+
+/// The kinds of definitions as they are reported by `INFO FOR DB` and
+/// `INFO FOR TABLE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DefinitionKind {
+  Namespace,
+  Database,
+  Table,
+  Field,
+  Index,
+  Event,
+  Scope,
+  Token,
+  Function,
+}
+
+impl DefinitionKind {
+  /// The keys under which the definitions of this kind are listed by the
+  /// `INFO FOR` statements, depending on the version of SurrealDB.
+  fn info_keys(&self) -> &'static [&'static str] {
+    match self {
+      DefinitionKind::Namespace => &["ns", "namespaces"],
+      DefinitionKind::Database => &["db", "databases"],
+      DefinitionKind::Table => &["tb", "tables"],
+      DefinitionKind::Field => &["fd", "fields"],
+      DefinitionKind::Index => &["ix", "indexes"],
+      DefinitionKind::Event => &["ev", "events"],
+      DefinitionKind::Scope => &["sc", "scopes"],
+      DefinitionKind::Token => &["dt", "tokens"],
+      DefinitionKind::Function => &["fc", "functions"],
+    }
+  }
+}
+
+/// What uniquely identifies a definition in the schema.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DefinitionKey {
+  pub kind: DefinitionKind,
+
+  /// The table the definition belongs to, for fields, indexes and events.
+  pub table: Option<String>,
+  pub name: String,
+}
+
+impl Definition {
+  pub fn key(&self) -> DefinitionKey {
+    let (kind, table, name) = match self {
+      Definition::Namespace(d) => (DefinitionKind::Namespace, None, &d.name),
+      Definition::Database(d) => (DefinitionKind::Database, None, &d.name),
+      Definition::Table(d) => (DefinitionKind::Table, None, &d.name),
+      Definition::Field(d) => (DefinitionKind::Field, Some(&d.table), &d.name),
+      Definition::Index(d) => (DefinitionKind::Index, Some(&d.table), &d.name),
+      Definition::Event(d) => (DefinitionKind::Event, Some(&d.table), &d.name),
+      Definition::Scope(d) => (DefinitionKind::Scope, None, &d.name),
+      Definition::Token(d) => (DefinitionKind::Token, None, &d.name),
+      Definition::Function(d) => (DefinitionKind::Function, None, &d.name),
+    };
+
+    DefinitionKey {
+      kind,
+      table: table.cloned(),
+      name: name.clone(),
+    }
+  }
+}
+
+impl Display for Definition {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Definition::Namespace(d) => d.fmt(f),
+      Definition::Database(d) => d.fmt(f),
+      Definition::Table(d) => d.fmt(f),
+      Definition::Field(d) => d.fmt(f),
+      Definition::Index(d) => d.fmt(f),
+      Definition::Event(d) => d.fmt(f),
+      Definition::Scope(d) => d.fmt(f),
+      Definition::Token(d) => d.fmt(f),
+      Definition::Function(d) => d.fmt(f),
+    }
+  }
+}
+
+macro_rules! impl_into_definition {
+  ($($variant:ident($define:ident)),*) => {
+    $(
+      impl From<$define> for Definition {
+        fn from(definition: $define) -> Self {
+          Definition::$variant(definition)
+        }
+      }
+    )*
+  };
+}
+
+impl_into_definition!(
+  Namespace(DefineNamespace),
+  Database(DefineDatabase),
+  Table(DefineTable),
+  Field(DefineField),
+  Index(DefineIndex),
+  Event(DefineEvent),
+  Scope(DefineScope),
+  Token(DefineToken),
+  Function(DefineFunction)
+);
+
+/// A set of definitions describing the desired schema.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+  definitions: Vec<Definition>,
+}
+
+impl Schema {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn define<D: Into<Definition>>(mut self, definition: D) -> Self {
+    self.definitions.push(definition.into());
+    self
+  }
+
+  pub fn definitions(&self) -> &[Definition] {
+    &self.definitions
+  }
+
+  /// Render every definition into a single SurrealQL string, one statement
+  /// per line.
+  pub fn to_surql(&self) -> String {
+    statements(&self.definitions)
+  }
+
+  /// Send every definition to the database. The `DEFINE` statements overwrite
+  /// the existing definitions with the same name.
+  pub async fn apply(&self, client: &mut SurrealClient) -> RpcResult<()> {
+    client
+      .send_checked_query(self.to_surql(), json!({}))
+      .await?;
+
+    Ok(())
+  }
+
+  /// Read the schema currently defined in the database and compare it with
+  /// this one.
+  ///
+  /// The comparison covers the tables, scopes, tokens and functions of the
+  /// current database, and the fields, indexes and events of the tables this
+  /// schema defines. The statements are compared in a canonical form, as
+  /// SurrealDB outputs them with a different case and whitespaces and with
+  /// the clauses it implies made explicit, see [canonicalize()].
+  pub async fn diff(&self, client: &mut SurrealClient) -> RpcResult<SchemaDiff> {
+    let mut tables: Vec<String> = Vec::new();
+    let defined_tables = self
+      .definitions
+      .iter()
+      .filter_map(|definition| match definition {
+        Definition::Table(DefineTable { name: table, .. })
+        | Definition::Field(DefineField { table, .. })
+        | Definition::Index(DefineIndex { table, .. })
+        | Definition::Event(DefineEvent { table, .. }) => Some(table.as_str()),
+        _ => None,
+      });
+
+    for table in defined_tables {
+      if !tables.iter().any(|t| t == table) {
+        tables.push(table.to_owned());
+      }
+    }
+
+    let mut current = read_definitions(client, Some(tables)).await?;
+    let mut diff = SchemaDiff::default();
+
+    for definition in &self.definitions {
+      let key = definition.key();
+
+      if matches!(
+        key.kind,
+        DefinitionKind::Namespace | DefinitionKind::Database
+      ) {
+        continue;
+      }
+
+      match current.remove(&key) {
+        None => diff.missing.push(definition.clone()),
+        Some(statement) if canonicalize(&statement) != canonicalize(&definition.to_string()) => {
+          diff.changed.push((definition.clone(), statement))
+        }
+        Some(_) => {}
+      };
+    }
+
+    diff.extra = current.into_iter().collect();
+
+    Ok(diff)
+  }
+}
+
+/// The differences between a [Schema] and the schema defined in the database.
+#[derive(Debug, Default)]
+pub struct SchemaDiff {
+  /// The definitions that don't exist in the database.
+  pub missing: Vec<Definition>,
+
+  /// The definitions that exist in the database with a different statement,
+  /// along with the statement currently defined.
+  pub changed: Vec<(Definition, String)>,
+
+  /// The definitions that exist in the database but not in the schema, along
+  /// with their statement.
+  pub extra: Vec<(DefinitionKey, String)>,
+}
+
+impl SchemaDiff {
+  pub fn is_empty(&self) -> bool {
+    self.missing.is_empty() && self.changed.is_empty() && self.extra.is_empty()
+  }
+
+  /// The statements that bring the database to the desired schema, without
+  /// removing the extra definitions.
+  pub fn to_surql(&self) -> String {
+    let definitions: Vec<Definition> = self
+      .missing
+      .iter()
+      .chain(self.changed.iter().map(|(definition, _)| definition))
+      .cloned()
+      .collect();
+
+    statements(&definitions)
+  }
+}
+
+fn statements(definitions: &[Definition]) -> String {
+  definitions
+    .iter()
+    .map(|definition| format!("{definition};\n"))
+    .collect()
+}
+
+/// Read every definition of the current database as SurrealDB outputs them:
+/// its tables, scopes, tokens and functions, and the fields, indexes and
+/// events of its tables.
+pub async fn read_database_definitions(
+  client: &mut SurrealClient,
+) -> RpcResult<BTreeMap<DefinitionKey, String>> {
+  read_definitions(client, None).await
+}
+
+/// Read the tables, scopes, tokens and functions defined in the current
+/// database, along with the fields, indexes and events of the `tables`, or of
+/// every table of the database if [None].
+pub(crate) async fn read_definitions(
+  client: &mut SurrealClient, tables: Option<Vec<String>>,
+) -> RpcResult<BTreeMap<DefinitionKey, String>> {
+  let mut definitions = BTreeMap::new();
+
+  let db_info = client
+    .find_one::<Value>("INFO FOR DB".to_owned(), json!({}))
+    .await?
+    .unwrap_or_default();
+
+  for kind in [
+    DefinitionKind::Table,
+    DefinitionKind::Scope,
+    DefinitionKind::Token,
+    DefinitionKind::Function,
+  ] {
+    read_info(&db_info, kind, None, &mut definitions);
+  }
+
+  let tables = tables.unwrap_or_else(|| {
+    definitions
+      .keys()
+      .filter(|key| key.kind == DefinitionKind::Table)
+      .map(|key| key.name.clone())
+      .collect()
+  });
+
+  for table in tables {
+    let table_info = client
+      .find_one::<Value>(
+        format!("INFO FOR TABLE {}", table_identifier(&table)),
+        json!({}),
+      )
+      .await?
+      .unwrap_or_default();
+
+    for kind in [
+      DefinitionKind::Field,
+      DefinitionKind::Index,
+      DefinitionKind::Event,
+    ] {
+      read_info(&table_info, kind, Some(&table), &mut definitions);
+    }
+  }
+
+  Ok(definitions)
+}
+
+/// Read the definitions of `kind` from the output of an `INFO FOR` statement.
+fn read_info(
+  info: &Value, kind: DefinitionKind, table: Option<&str>,
+  definitions: &mut BTreeMap<DefinitionKey, String>,
+) {
+  let entries = kind
+    .info_keys()
+    .iter()
+    .find_map(|key| info.get(key))
+    .and_then(Value::as_object);
+
+  for (name, statement) in entries.into_iter().flatten() {
+    if let Some(statement) = statement.as_str() {
+      let key = DefinitionKey {
+        kind,
+        table: table.map(str::to_owned),
+        name: name.clone(),
+      };
+
+      definitions.insert(key, statement.to_owned());
+    }
+  }
+}
+
+/// The actions of the `PERMISSIONS` clauses, in the order they are listed in
+/// the canonical form of the definitions.
+const PERMISSION_ACTIONS: [&str; 4] = ["select", "create", "update", "delete"];
+
+/// The form of a `DEFINE` statement that is compared by [`Schema::diff()`],
+/// where the case and whitespaces are normalized and where:
+/// - the `PERMISSIONS` of the tables and fields list a rule for every action,
+///   the permissions of a table are `NONE` and the ones of a field are `FULL`
+///   when the clause is missing, and the actions a `FOR` list leaves out are
+///   `FULL`.
+/// - the `TYPE ANY` and `TYPE NORMAL` of the tables are left out, as they
+///   are the types of the tables that don't define one.
+/// - the optional `TABLE` keyword of the `ON TABLE` clauses is left out.
+fn canonicalize(statement: &str) -> String {
+  let statement = normalize(statement);
+  let (tokens, _) = syntax::tokenize(&statement);
+  let words: Vec<&str> = tokens
+    .iter()
+    .take(2)
+    .filter_map(|token| match &token.kind {
+      TokenKind::Word(_) => Some(&statement[token.start..token.end]),
+      _ => None,
+    })
+    .collect();
+
+  let is_table = match words.as_slice() {
+    ["define", "table"] => true,
+    ["define", "field"] => false,
+    _ => return without_on_table(&statement),
+  };
+
+  // the permissions are the last clause of the definitions
+  let mut depth = 0_usize;
+  let permissions = tokens.iter().find(|token| {
+    match token.kind {
+      TokenKind::Open(_) => depth += 1,
+      TokenKind::Close(_) => depth = depth.saturating_sub(1),
+      _ => {}
+    };
+
+    depth == 0 && token.kind == TokenKind::Word("permissions".to_owned())
+  });
+
+  let (head, clause) = match permissions {
+    Some(token) => (
+      &statement[..token.start],
+      Some(statement[token.end..].trim()),
+    ),
+    None => (statement.as_str(), None),
+  };
+
+  let (head, default_permission) = match is_table {
+    true => (
+      format!(" {head} ")
+        .replacen(" type any ", " ", 1)
+        .replacen(" type normal ", " ", 1),
+      "none",
+    ),
+    false => (without_on_table(head), "full"),
+  };
+
+  let rules = permission_rules(clause, default_permission);
+  let rules: Vec<String> = PERMISSION_ACTIONS
+    .iter()
+    .zip(rules)
+    .map(|(action, rule)| format!("for {action} {rule}"))
+    .collect();
+
+  format!("{} permissions {}", head.trim(), rules.join(" "))
+}
+
+fn without_on_table(statement: &str) -> String {
+  format!(" {statement} ")
+    .replacen(" on table ", " on ", 1)
+    .trim()
+    .to_owned()
+}
+
+/// Read the rule of every action, in the order of [PERMISSION_ACTIONS], from
+/// a normalized `PERMISSIONS` clause: `none`, `full`, or a list of
+/// `for <actions> <rule>` separated by commas or spaces.
+fn permission_rules(clause: Option<&str>, default_permission: &str) -> Vec<String> {
+  let clause = match clause {
+    None => return vec![default_permission.to_owned(); PERMISSION_ACTIONS.len()],
+    Some(rule @ ("none" | "full")) => return vec![rule.to_owned(); PERMISSION_ACTIONS.len()],
+    Some(clause) => clause,
+  };
+
+  let (tokens, _) = syntax::tokenize(clause);
+  let mut groups: Vec<(Vec<&str>, usize, usize)> = Vec::new();
+  let mut depth = 0_usize;
+  let mut in_actions = false;
+
+  for token in &tokens {
+    let text = &clause[token.start..token.end];
+
+    match token.kind {
+      TokenKind::Word(_) if depth == 0 && text == "for" => {
+        groups.push((Vec::new(), token.end, token.end));
+        in_actions = true;
+        continue;
+      }
+      TokenKind::Word(_) if in_actions && PERMISSION_ACTIONS.contains(&text) => {
+        if let Some((actions, start, end)) = groups.last_mut() {
+          actions.push(text);
+          (*start, *end) = (token.end, token.end);
+        }
+
+        continue;
+      }
+      TokenKind::Symbol(',') if in_actions => continue,
+      TokenKind::Open(_) => depth += 1,
+      TokenKind::Close(_) => depth = depth.saturating_sub(1),
+      _ => {}
+    };
+
+    in_actions = false;
+
+    if let Some((_, _, end)) = groups.last_mut() {
+      *end = token.end;
+    }
+  }
+
+  PERMISSION_ACTIONS
+    .iter()
+    .map(|action| {
+      groups
+        .iter()
+        .find(|(actions, _, _)| actions.contains(action))
+        .map(|(_, start, end)| clause[*start..*end].trim().trim_end_matches(',').trim())
+        .unwrap_or("full")
+        .to_owned()
+    })
+    .collect()
+}
+
+/// Lowercase the `statement` and collapse its whitespaces and comments into a
+/// single space, without its trailing `;`. The strings are left as they are
+/// since their case matters.
+fn normalize(statement: &str) -> String {
+  let (mut tokens, _) = syntax::tokenize(statement);
+
+  while tokens
+    .last()
+    .is_some_and(|token| token.kind == TokenKind::Symbol(';'))
+  {
+    tokens.pop();
+  }
+
+  let mut normalized = String::with_capacity(statement.len());
+  let mut previous_end = None;
+
+  for token in &tokens {
+    let text = &statement[token.start..token.end];
+
+    if previous_end.is_some_and(|end| end < token.start) {
+      normalized.push(' ');
+    }
+
+    match token.kind {
+      TokenKind::String => normalized.push_str(text),
+      _ => normalized.push_str(&text.to_lowercase()),
+    };
+
+    previous_end = Some(token.end);
+  }
+
+  normalized
+}
@@ -0,0 +1,50 @@
+use crate::Query;
+use crate::SurrealClient;
+
+/// A handle to a [SurrealClient] whose queries are guaranteed to run under a
+/// namespace and database, obtained through [`SurrealClient::scoped()`].
+///
+/// The handle borrows the client exclusively, and its queries issue a `use`
+/// before being sent whenever the session of the connection is under another
+/// namespace or database. Scoping a client borrowed from a
+/// [SurrealPool](crate::SurrealPool) works the same way, the pooled client
+/// stays out of the pool for as long as the handle lives:
+/// ```rust,ignore
+/// let mut client = pool.get().await?;
+/// let mut tenant = client.scoped("tenants", &tenant_name);
+///
+/// let users: Vec<User> = tenant.query("select * from user").find_many().await?;
+/// ```
+pub struct ScopedClient<'a> {
+  client: &'a mut SurrealClient,
+  namespace: String,
+  database: String,
+}
+
+impl<'a> ScopedClient<'a> {
+  pub(crate) fn new(client: &'a mut SurrealClient, namespace: String, database: String) -> Self {
+    Self {
+      client,
+      namespace,
+      database,
+    }
+  }
+
+  pub fn namespace(&self) -> &str {
+    &self.namespace
+  }
+
+  pub fn database(&self) -> &str {
+    &self.database
+  }
+
+  /// Start building a [Query] that runs under the namespace and database of
+  /// the handle, see [`SurrealClient::query()`]. The session is switched to
+  /// them when the query is sent, if it isn't already.
+  pub fn query<S: Into<String>>(&mut self, query: S) -> Query<'_> {
+    self
+      .client
+      .query(query)
+      .scoped(self.namespace.clone(), self.database.clone())
+  }
+}
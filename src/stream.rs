@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+
+use futures::Stream;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::rpc::RpcChannelError;
+use crate::rpc::RpcResult;
+use crate::syntax;
+use crate::SurrealClient;
+
+/// The parameters that are added to the paginated queries, their names are
+/// unlikely to conflict with the user's parameters.
+const PAGE_START_PARAM: &str = "__page_start";
+const PAGE_LIMIT_PARAM: &str = "__page_limit";
+
+/// The state of a paginated query between two pages.
+struct PageCursor<'a> {
+  client: &'a mut SurrealClient,
+  query: String,
+  params: Value,
+  page_size: usize,
+  start: usize,
+
+  /// The rows of the current page that were not yielded yet.
+  buffer: VecDeque<Value>,
+
+  /// Set once the last page was fetched, or after an error.
+  exhausted: bool,
+}
+
+impl<'a> PageCursor<'a> {
+  async fn next_page(&mut self) -> RpcResult<()> {
+    let mut params = self.params.clone();
+    params[PAGE_START_PARAM] = Value::from(self.start);
+    params[PAGE_LIMIT_PARAM] = Value::from(self.page_size);
+
+    let rows = match self
+      .client
+      .find_many_values(self.query.clone(), params)
+      .await
+    {
+      Ok(rows) => rows,
+      Err(error) => return Err(self.fail(error)),
+    };
+
+    self.exhausted = rows.len() < self.page_size;
+    self.start += rows.len();
+    self.buffer.extend(rows);
+
+    Ok(())
+  }
+
+  /// End the stream after the `error`, the rows left in the current page are
+  /// dropped.
+  fn fail(&mut self, error: RpcChannelError) -> RpcChannelError {
+    self.exhausted = true;
+    self.buffer.clear();
+
+    error
+  }
+}
+
+/// Turn the `query` into a [Stream] that fetches its rows `page_size` at a
+/// time using `LIMIT` and `START` clauses. Refer to [`SurrealClient::stream()`].
+pub(crate) fn paginate<T: DeserializeOwned>(
+  client: &mut SurrealClient, query: String, params: Value, page_size: usize,
+) -> impl Stream<Item = RpcResult<T>> + '_ {
+  let params = match params {
+    Value::Null => Ok(Value::Object(Default::default())),
+    Value::Object(fields) => Ok(Value::Object(fields)),
+    _ => Err(RpcChannelError::InvalidBinding {
+      message: "the parameters of a paginated query must be an object".to_owned(),
+    }),
+  };
+
+  let statement =
+    syntax::single_statement(&query).map_err(|inner| RpcChannelError::SyntaxError { inner });
+
+  // the statement is wrapped so the clauses are appended after its own ones,
+  // and out of reach of a comment that would end it
+  let (query, params, error) = match statement.and_then(|statement| Ok((statement, params?))) {
+    Ok((statement, params)) => (
+      format!(
+        "SELECT * FROM (\n{statement}\n) LIMIT ${PAGE_LIMIT_PARAM} START ${PAGE_START_PARAM}"
+      ),
+      params,
+      None,
+    ),
+    Err(error) => (String::new(), Value::Null, Some(error)),
+  };
+
+  let cursor = PageCursor {
+    client,
+    query,
+    params,
+    page_size: page_size.max(1),
+    start: 0,
+    buffer: VecDeque::new(),
+    exhausted: error.is_some(),
+  };
+
+  futures::stream::unfold((cursor, error), |(mut cursor, error)| async move {
+    if let Some(error) = error {
+      return Some((Err(error), (cursor, None)));
+    }
+
+    loop {
+      if let Some(row) = cursor.buffer.pop_front() {
+        let item = serde_json::from_value::<T>(row).map_err(|error| cursor.fail(error.into()));
+
+        return Some((item, (cursor, None)));
+      }
+
+      if cursor.exhausted {
+        return None;
+      }
+
+      if let Err(error) = cursor.next_page().await {
+        return Some((Err(error), (cursor, None)));
+      }
+    }
+  })
+}
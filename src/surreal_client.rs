@@ -1,95 +1,227 @@
-use std::collections::HashMap;
+use std::sync::Arc;
 
-use futures::stream::SplitSink;
 use futures::Future;
-use futures::SinkExt;
-use futures::StreamExt;
+use futures::Stream;
 use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::json;
+use serde_json::Map;
 use serde_json::Value;
-use tokio::net::TcpStream;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
-use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::MaybeTlsStream;
-use tokio_tungstenite::WebSocketStream;
-
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+
+use crate::bulk;
+use crate::bulk::BulkInsertChunk;
+use crate::compression;
+use crate::compression::Compression;
+use crate::compression::CompressionCounters;
+use crate::compression::CompressionStats;
+use crate::connection;
+use crate::connection::ConnectionState;
+use crate::connection::OutgoingFrame;
+use crate::connection::PendingRequest;
+use crate::dump;
+use crate::dump::DumpResult;
+use crate::dump::ImportProgress;
+use crate::flow::ClientMetrics;
+use crate::flow::FlowControl;
+use crate::flow::InFlightLimit;
+use crate::flow::OUTGOING_QUEUE_CAPACITY;
+use crate::graph;
+use crate::graph::Direction;
+use crate::protocol::Protocol;
+use crate::response::SurrealResponseResult;
+use crate::retry;
+use crate::retry::RetryPolicy;
+use crate::rpc::RpcChannelError;
 use crate::rpc::RpcResult;
+use crate::stream;
+use crate::syntax;
+use crate::Batch;
+use crate::Query;
+use crate::RecordId;
+use crate::ScopedClient;
+use crate::ServerVersion;
 use crate::SurrealMessage;
 use crate::SurrealResponseData;
 
-type SurrealResponseSender = oneshot::Sender<SurrealResponseData>;
+/// The code of the RPC error of the methods the instance doesn't implement.
+const METHOD_NOT_FOUND: i32 = -32601;
 
 #[derive(Debug)]
 pub struct SurrealResponse {
-  receiver: oneshot::Receiver<SurrealResponseData>,
+  receiver: oneshot::Receiver<RpcResult<SurrealResponseData>>,
 }
 impl Future for SurrealResponse {
-  type Output = <oneshot::Receiver<SurrealResponseData> as Future>::Output;
+  type Output = RpcResult<SurrealResponseData>;
 
   fn poll(
     self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>,
   ) -> std::task::Poll<Self::Output> {
     // SAFETY
     // As long as nothing ever hands out an `&(mut) Receiver` this is safe.
-    unsafe {
-      self
-        .map_unchecked_mut(|response| &mut response.receiver)
-        .poll(cx)
-    }
+    let receiver = unsafe { self.map_unchecked_mut(|response| &mut response.receiver) };
+
+    // the sender is dropped without a response once the connection is closed
+    receiver
+      .poll(cx)
+      .map(|result| result.unwrap_or(Err(RpcChannelError::ClientClosed)))
   }
 }
 
 pub struct SurrealClient {
-  socket_sink: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
-  resp_sink: mpsc::UnboundedSender<(String, SurrealResponseSender)>,
+  outgoing: mpsc::Sender<OutgoingFrame>,
+  connection: Arc<ConnectionState>,
+  protocol: Protocol,
+
+  /// The id of the next request, the ids are unique for the lifetime of the
+  /// connection.
+  next_request_id: u64,
+  in_flight_limit: InFlightLimit,
+  server_version: Option<ServerVersion>,
+
+  /// The namespace and database of the session, refer to [`Self::use_namespace()`].
+  session_scope: Option<(String, String)>,
+
+  /// The variables defined for the session, refer to [`Self::set_var()`].
+  session_vars: Map<String, Value>,
+  retry_policy: RetryPolicy,
+  syntax_checks: bool,
+
+  /// The counters of the compression, if it was offered to the instance.
+  compression: Option<Arc<CompressionCounters>>,
 }
 
 impl SurrealClient {
   pub async fn new(url: &str) -> RpcResult<Self> {
-    let (socket, _) = tokio_tungstenite::connect_async(url).await?;
-    let (socket_sink, mut socket_stream) = socket.split();
-    let (resp_sink, resp_stream) = tokio::sync::mpsc::unbounded_channel();
-    let mut recv_stream = tokio_stream::wrappers::UnboundedReceiverStream::new(resp_stream);
-
-    tokio::spawn(async move {
-      let mut requests: HashMap<String, SurrealResponseSender> = HashMap::new();
-
-      loop {
-        tokio::select! {
-            receiver = recv_stream.next() => {
-              if let Some((id, sender)) = receiver {
-                  requests.insert(id, sender);
-              }
-            },
-
-            res = socket_stream.next() => {
-              if let Some(Ok(Message::Text(json_message))) = res {
-                match serde_json::from_str::<SurrealResponseData>(&json_message) {
-                  Ok(response) => if let Some(sender) = requests.remove(&response.id) {
-                    if let Err(_) = sender.send(response) {
-                      // do nothing at the moment, an error from a .send() call
-                      // means the receiver is no longer listening. Which is a
-                      // possible & valid state.
-                    }
-                  },
-                  Err(_) => {
-                    // TODO: this error should be handled, probably by sending
-                    // it through the `sender`. But that would require the
-                    // `SurrealResponseSender` to accept an enum as it only accepts
-                    // valid data at the moment.
-                  },
-                };
-              }
-            },
-        }
+    Self::connect(url, None, None).await
+  }
+
+  /// Connect to the SurrealDB instance and offer it the `protocol` through the
+  /// websocket subprotocol, with JSON as a fallback. The protocol the instance
+  /// picked is then used for every message, refer to [`Self::protocol()`].
+  ///
+  /// The instances that don't negotiate the subprotocol use JSON.
+  pub async fn with_protocol(url: &str, protocol: Protocol) -> RpcResult<Self> {
+    Self::connect(url, Some(protocol), None).await
+  }
+
+  /// Connect to the SurrealDB instance like [`Self::with_protocol()`], and
+  /// offer it to compress the messages with the `permessage-deflate`
+  /// websocket extension. The messages of at least the threshold of the
+  /// `compression` are then sent compressed, and the instance compresses the
+  /// messages it chooses to.
+  ///
+  /// The instances that decline the extension get uncompressed messages, refer
+  /// to [`Self::compression_stats()`]. The compression is only available with
+  /// the `ws` urls.
+  pub async fn with_compression(
+    url: &str, protocol: Protocol, compression: Compression,
+  ) -> RpcResult<Self> {
+    Self::connect(url, Some(protocol), Some(compression)).await
+  }
+
+  async fn connect(
+    url: &str, preferred_protocol: Option<Protocol>, compression: Option<Compression>,
+  ) -> RpcResult<Self> {
+    let mut request = url.into_client_request()?;
+
+    if let Some(preferred_protocol) = preferred_protocol {
+      request.headers_mut().insert(
+        "Sec-WebSocket-Protocol",
+        HeaderValue::from_static(preferred_protocol.offered_subprotocols()),
+      );
+    }
+
+    let (outgoing, connection, handshake, counters) = match compression {
+      None => {
+        let (socket, handshake) = tokio_tungstenite::connect_async(request).await?;
+        let (outgoing, connection) = connection::spawn(socket);
+
+        (outgoing, connection, handshake, None)
       }
-    });
+      Some(compression) => {
+        request.headers_mut().insert(
+          "Sec-WebSocket-Extensions",
+          HeaderValue::from_static(compression::DEFLATE_OFFER),
+        );
+
+        let counters = Arc::new(CompressionCounters::default());
+        let stream = compression::connect(&request, compression, counters.clone()).await?;
+        let (socket, handshake) = tokio_tungstenite::client_async(request, stream).await?;
+        let (outgoing, connection) = connection::spawn(socket);
+
+        (outgoing, connection, handshake, Some(counters))
+      }
+    };
+
+    let protocol = handshake
+      .headers()
+      .get("Sec-WebSocket-Protocol")
+      .and_then(|value| value.to_str().ok())
+      .and_then(Protocol::from_name)
+      .unwrap_or(Protocol::Json);
+
+    let mut client = Self {
+      outgoing,
+      connection,
+      protocol,
+      next_request_id: 0,
+      in_flight_limit: InFlightLimit::new(FlowControl::unlimited()),
+      server_version: None,
+      session_scope: None,
+      session_vars: Map::new(),
+      retry_policy: RetryPolicy::none(),
+      syntax_checks: false,
+      compression: counters,
+    };
+
+    client.server_version = client.fetch_server_version().await?;
 
-    Ok(Self {
-      socket_sink,
-      resp_sink,
-    })
+    Ok(client)
+  }
+
+  /// Ask the instance for its version with the `version` RPC method, the
+  /// instances that answer with an unsupported version are rejected. The
+  /// version is unknown if the instance doesn't implement the method.
+  async fn fetch_server_version(&mut self) -> RpcResult<Option<ServerVersion>> {
+    let unsupported = |version: &str| RpcChannelError::UnsupportedServer {
+      version: version.to_owned(),
+    };
+
+    let response = match self.send_message("version", json!([])).await?.await {
+      Ok(response) => response,
+      Err(RpcChannelError::SurrealQueryError { inner })
+        if inner.code() == METHOD_NOT_FOUND || inner.message() == "Method not found" =>
+      {
+        return Ok(None)
+      }
+      Err(error) => return Err(error),
+    };
+
+    let SurrealResponseResult::String(version) = response.result else {
+      return Err(unsupported("unknown"));
+    };
+
+    match version.parse::<ServerVersion>() {
+      Ok(server_version) if server_version.is_supported() => Ok(Some(server_version)),
+      _ => Err(unsupported(&version)),
+    }
+  }
+
+  /// The version of the SurrealDB instance, which was checked to be supported
+  /// when connecting, or [None] if the instance doesn't report its version.
+  ///
+  /// The version is only used to refuse the unsupported instances, the
+  /// requests are the same for every version. The responses of all the
+  /// supported versions are handled by [SurrealResponseData], whose statement
+  /// results accept both the shape of the betas and the one of the releases.
+  pub fn server_version(&self) -> Option<&ServerVersion> {
+    self.server_version.as_ref()
   }
 
   pub async fn signin<T: AsRef<str>>(&mut self, user: T, pass: T) -> RpcResult<()>
@@ -114,45 +246,317 @@ impl SurrealClient {
   where
     String: From<T>,
   {
+    let (namespace, database) = (String::from(namespace), String::from(database));
+
+    // the session is in an unknown state until the instance answers
+    self.session_scope = None;
     self
-      .send_message(
-        "use",
-        json!([String::from(namespace), String::from(database)]),
-      )
+      .send_message("use", json!([namespace, database]))
       .await?
       .await?;
 
+    self.session_scope = Some((namespace, database));
+
     Ok(())
   }
 
+  /// The namespace and database the session was switched to with
+  /// [`Self::use_namespace()`], if any. It is forgotten when the session is
+  /// switched another way, with a `use` request or a query with a `USE`
+  /// statement.
+  pub fn session_scope(&self) -> Option<(&str, &str)> {
+    self
+      .session_scope
+      .as_ref()
+      .map(|(namespace, database)| (namespace.as_str(), database.as_str()))
+  }
+
+  /// Get a handle whose queries are guaranteed to run under the `namespace`
+  /// and `database`, see [ScopedClient].
+  pub fn scoped<N: Into<String>, D: Into<String>>(
+    &mut self, namespace: N, database: D,
+  ) -> ScopedClient<'_> {
+    ScopedClient::new(self, namespace.into(), database.into())
+  }
+
+  /// Queue a request for the `method` and return its pending response, the
+  /// requests are written to the socket in the order they were queued.
+  ///
+  /// The call waits for a free slot when the outgoing queue or the
+  /// [FlowControl] limit is reached, or fails with a
+  /// [`RpcChannelError::Overloaded`] if the flow control rejects the requests
+  /// over the limit. Once the connection is closed every call fails with a
+  /// [`RpcChannelError::ClientClosed`].
   pub async fn send_message(
     &mut self, method: &'static str, params: Value,
   ) -> RpcResult<SurrealResponse> {
-    const ALPHABET: [char; 36] = [
-      '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h',
-      'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
-    ];
+    self.next_request_id += 1;
 
     let message = SurrealMessage {
-      id: nanoid::nanoid!(10, &ALPHABET),
+      id: self.next_request_id.to_string(),
       method: method.to_owned(),
       params,
     };
 
+    let switches_session = match method {
+      "use" => true,
+      "query" => message.params[0]
+        .as_str()
+        .is_some_and(syntax::has_use_statement),
+      _ => false,
+    };
+
+    // the scope is set again by `use_namespace` once the instance answered
+    if switches_session {
+      self.session_scope = None;
+    }
+
     let (tx, rx) = oneshot::channel();
+    let frame = self.protocol.encode(&message)?;
+    let permit = self.in_flight_limit.acquire().await?;
+
+    self.connection.register(
+      message.id.clone(),
+      PendingRequest {
+        sender: tx,
+        _permit: permit,
+      },
+    )?;
+
+    // the task that writes to the socket stops once the connection is closed
+    if self
+      .outgoing
+      .send((message.id.clone(), frame))
+      .await
+      .is_err()
+    {
+      self
+        .connection
+        .resolve(&message.id, Err(RpcChannelError::ClientClosed));
 
-    self.resp_sink.send((message.id.clone(), tx)).unwrap();
-    self
-      .socket_sink
-      .send(Message::Text(serde_json::to_string(&message).unwrap()))
-      .await?;
+      return Err(RpcChannelError::ClientClosed);
+    }
 
     Ok(SurrealResponse { receiver: rx })
   }
 
+  /// Set the [FlowControl] of the requests, new clients don't limit them. The
+  /// requests in flight count against the new limit.
+  pub fn set_flow_control(&mut self, flow_control: FlowControl) {
+    self.in_flight_limit.set_flow_control(flow_control);
+  }
+
+  pub fn flow_control(&self) -> &FlowControl {
+    self.in_flight_limit.flow_control()
+  }
+
+  /// Get the amount of requests that are queued or await their response.
+  pub fn metrics(&self) -> ClientMetrics {
+    ClientMetrics {
+      in_flight: self.connection.in_flight(),
+      queued: self.outgoing.max_capacity() - self.outgoing.capacity(),
+      max_in_flight: self.flow_control().max_in_flight,
+      queue_capacity: OUTGOING_QUEUE_CAPACITY,
+      unmatched_responses: self.connection.unmatched_responses(),
+    }
+  }
+
+  /// The amount of messages and bytes that were compressed since the client
+  /// connected, or [None] if the compression wasn't offered to the instance
+  /// or if it declined it, refer to [`Self::with_compression()`].
+  pub fn compression_stats(&self) -> Option<CompressionStats> {
+    self
+      .compression
+      .as_ref()
+      .filter(|counters| counters.is_negotiated())
+      .map(|counters| counters.stats())
+  }
+
+  /// Whether the connection is closed, in which case every request fails with
+  /// a [`RpcChannelError::ClientClosed`] and the client must be replaced.
+  pub fn is_closed(&self) -> bool {
+    self.connection.is_closed()
+  }
+
+  /// The [Protocol] that was negotiated with the SurrealDB instance.
+  pub fn protocol(&self) -> Protocol {
+    self.protocol
+  }
+
+  /// Start building a [Query] whose parameters are bound with typed values
+  /// rather than a hand-written JSON object.
+  pub fn query<S: Into<String>>(&mut self, query: S) -> Query<'_> {
+    Query::new(self, query.into())
+  }
+
+  /// Start building a [Batch] of calls that are pipelined over the socket,
+  /// rather than sent one after the other once the previous one got its
+  /// response.
+  pub fn batch(&mut self) -> Batch<'_> {
+    Batch::new(self)
+  }
+
   /// Send a query using the current socket connection then return the raw [SurrealResponse]
+  ///
+  /// The [session variables](Self::session_vars) are merged into the `params`.
+  /// If the syntax checks are enabled, refer to [`Self::set_syntax_checks()`],
+  /// the query is checked before being sent.
   pub async fn send_query(&mut self, query: String, params: Value) -> RpcResult<SurrealResponse> {
-    Ok(self.send_message("query", json!([query, params])).await?)
+    let params = self.merge_session_vars(params);
+
+    if self.syntax_checks {
+      syntax::check_query(&query, &params)
+        .map_err(|inner| RpcChannelError::SyntaxError { inner })?;
+    }
+
+    self.send_message("query", json!([query, params])).await
+  }
+
+  /// Define the `$name` variable for the rest of the session with the `let`
+  /// RPC method. The variable is also kept by the client, so it can be listed
+  /// with [`Self::session_vars()`] and is merged into the params of every
+  /// query, where the params that are passed explicitly take precedence.
+  pub async fn set_var<K: Into<String>, V: Serialize>(
+    &mut self, name: K, value: V,
+  ) -> RpcResult<()> {
+    let name = name.into();
+    let value = serde_json::to_value(value)?;
+
+    self
+      .send_message("let", json!([name, value]))
+      .await?
+      .await?;
+
+    self.session_vars.insert(name, value);
+
+    Ok(())
+  }
+
+  /// Remove the `$name` variable from the session with the `unset` RPC method.
+  pub async fn unset_var(&mut self, name: &str) -> RpcResult<()> {
+    self.send_message("unset", json!([name])).await?.await?;
+    self.session_vars.remove(name);
+
+    Ok(())
+  }
+
+  /// The variables that were defined for the session with [`Self::set_var()`].
+  pub fn session_vars(&self) -> &Map<String, Value> {
+    &self.session_vars
+  }
+
+  /// Define every variable of `vars` for the session. This restores the
+  /// [session variables](Self::session_vars) of a previous connection after
+  /// reconnecting, since they are lost with the connection:
+  /// ```rust,ignore
+  /// let vars = client.session_vars().clone();
+  ///
+  /// client = SurrealClient::new(url).await?;
+  /// client.restore_session_vars(vars).await?;
+  /// ```
+  pub async fn restore_session_vars(&mut self, vars: Map<String, Value>) -> RpcResult<()> {
+    for (name, value) in vars {
+      self.set_var(name, value).await?;
+    }
+
+    Ok(())
+  }
+
+  /// Add the session variables to the `params`, without overwriting the params
+  /// that were passed explicitly.
+  fn merge_session_vars(&self, params: Value) -> Value {
+    match params {
+      Value::Object(mut fields) => {
+        for (name, value) in &self.session_vars {
+          fields.entry(name).or_insert_with(|| value.clone());
+        }
+
+        Value::Object(fields)
+      }
+      Value::Null if !self.session_vars.is_empty() => Value::Object(self.session_vars.clone()),
+      params => params,
+    }
+  }
+
+  /// Enable or disable the client-side syntax checks of the queries. When
+  /// enabled, the queries with unbalanced delimiters, unterminated strings or
+  /// comments, or with parameters that are missing from their `params` are
+  /// rejected with a [`RpcChannelError::SyntaxError`] without being sent.
+  ///
+  /// The checks are disabled by default.
+  pub fn set_syntax_checks(&mut self, enabled: bool) {
+    self.syntax_checks = enabled;
+  }
+
+  /// Set the [RetryPolicy] of the read-only and idempotent queries, new clients
+  /// never retry.
+  pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+    self.retry_policy = retry_policy;
+  }
+
+  pub fn retry_policy(&self) -> &RetryPolicy {
+    &self.retry_policy
+  }
+
+  /// Send a query and wait for its response, any statement of the query that
+  /// failed is turned into an error. The query is retried according to the
+  /// [RetryPolicy] if it is read-only.
+  pub(crate) async fn send_checked_query(
+    &mut self, query: String, params: Value,
+  ) -> RpcResult<SurrealResponseData> {
+    let retryable = retry::is_read_only(&query);
+
+    self.send_retryable_query(query, params, retryable).await
+  }
+
+  /// Same as [`Self::send_checked_query()`] except the caller decides whether
+  /// the query is safe to retry.
+  pub(crate) async fn send_retryable_query(
+    &mut self, query: String, params: Value, retryable: bool,
+  ) -> RpcResult<SurrealResponseData> {
+    if !retryable || self.retry_policy.max_retries == 0 {
+      return self.send_checked_query_once(query, params).await;
+    }
+
+    let mut retry = 0;
+
+    loop {
+      match self
+        .send_checked_query_once(query.clone(), params.clone())
+        .await
+      {
+        // a socket error leaves the client closed, sending the query again
+        // would only fail with a `ClientClosed`
+        Err(error)
+          if error.is_transient() && !self.is_closed() && retry < self.retry_policy.max_retries =>
+        {
+          tokio::time::sleep(self.retry_policy.backoff(retry)).await;
+          retry += 1;
+        }
+        result => return result,
+      };
+    }
+  }
+
+  async fn send_checked_query_once(
+    &mut self, query: String, params: Value,
+  ) -> RpcResult<SurrealResponseData> {
+    let response = self.send_query(query.clone(), params).await?;
+    let result = match self.retry_policy.timeout {
+      Some(duration) => tokio::time::timeout(duration, response)
+        .await
+        .map_err(|_| RpcChannelError::Timeout { duration })
+        .and_then(|result| result),
+      None => response.await,
+    };
+
+    result
+      .and_then(|response| {
+        response.check_statements()?;
+
+        Ok(response)
+      })
+      .map_err(|error| error.with_query_snippet(&query))
   }
 
   /// Send a query using the current socket connection then return the **first** [Value]
@@ -160,7 +564,7 @@ impl SurrealClient {
   ///
   /// Use [`Self::find_one()`] instead to get a typed return value.
   async fn find_one_value(&mut self, query: String, params: Value) -> RpcResult<Option<Value>> {
-    let response = self.send_query(query, params).await?.await?;
+    let response = self.send_checked_query(query, params).await?;
 
     Ok(
       response
@@ -171,6 +575,9 @@ impl SurrealClient {
 
   /// Send a query using the current socket connection then return the **first** [T]
   /// from the received [SurrealResponse].
+  ///
+  /// If any statement of the query failed, its error is returned as a
+  /// [`RpcChannelError::SurrealStatementError`] rather than the rows.
   pub async fn find_one<T: DeserializeOwned>(
     &mut self, query: String, params: Value,
   ) -> RpcResult<Option<T>> {
@@ -188,10 +595,12 @@ impl SurrealClient {
 
   /// Fetch the value for the given `key` out of the first row that is returned by
   /// the supplied `query`. If the key is missing then [None] is returned.
+  ///
+  /// The failed statements are returned as errors, like in [`Self::find_one()`].
   pub async fn find_one_key<T: DeserializeOwned>(
     &mut self, key: &str, query: String, params: Value,
   ) -> RpcResult<Option<T>> {
-    let response = self.send_query(query, params).await?.await?;
+    let response = self.send_checked_query(query, params).await?;
 
     let value = response
       .get_nth_query_result(0)
@@ -211,19 +620,23 @@ impl SurrealClient {
   /// from the received [SurrealResponse]
   ///
   /// Use [`Self::find_many()`] instead to get a typed return value.
-  async fn find_many_values(&mut self, query: String, params: Value) -> RpcResult<Vec<Value>> {
-    let response = self.send_query(query, params).await?.await?;
+  pub(crate) async fn find_many_values(
+    &mut self, query: String, params: Value,
+  ) -> RpcResult<Vec<Value>> {
+    let response = self.send_checked_query(query, params).await?;
 
     Ok(
       response
         .get_nth_query_result(0)
-        .and_then(|query_results| Some(query_results.results().clone()))
+        .map(|query_results| query_results.results().clone())
         .unwrap_or_default(),
     )
   }
 
   /// Send a query using the current socket connection then return the many [`<T>`]
   /// from the received [SurrealResponse].
+  ///
+  /// The failed statements are returned as errors, like in [`Self::find_one()`].
   pub async fn find_many<T: DeserializeOwned>(
     &mut self, query: String, params: Value,
   ) -> RpcResult<Vec<T>> {
@@ -233,17 +646,89 @@ impl SurrealClient {
     Ok(deser_result)
   }
 
+  /// Send the `query` in pages of `page_size` rows and return a [Stream] of
+  /// the [`<T>`] it returns, so large result sets are processed in bounded
+  /// memory.
+  ///
+  /// The pages are fetched by wrapping the supplied `query` in a `SELECT` with
+  /// `LIMIT` and `START` clauses, the `query` can therefore have any clause of
+  /// its own but must be a single statement. A query made of more statements
+  /// is rejected with a [`RpcChannelError::SyntaxError`]. The `params` must be
+  /// an object or [`Value::Null`].
+  ///
+  /// The stream ends after the first error it yields, including the rows that
+  /// failed to deserialize.
+  pub fn stream<T: DeserializeOwned>(
+    &mut self, query: String, params: Value, page_size: usize,
+  ) -> impl Stream<Item = RpcResult<T>> + '_ {
+    stream::paginate(self, query, params, page_size)
+  }
+
+  /// Insert the `records` into `table` using one `INSERT INTO` statement for
+  /// every chunk of `chunk_size` records. The chunks are pipelined over the
+  /// socket with at most [`crate::DEFAULT_BULK_CONCURRENCY`] of them awaiting
+  /// their response at once.
+  ///
+  /// A [BulkInsertChunk] is returned for every chunk, in order, holding either
+  /// the ids of the created records or the error that caused the chunk to be
+  /// rejected. A chunk failing doesn't prevent the next ones from being sent.
+  pub async fn insert_bulk<T: Serialize, I: IntoIterator<Item = T>>(
+    &mut self, table: &str, records: I, chunk_size: usize,
+  ) -> RpcResult<Vec<BulkInsertChunk>> {
+    self
+      .insert_bulk_with_concurrency(table, records, chunk_size, bulk::DEFAULT_BULK_CONCURRENCY)
+      .await
+  }
+
+  /// Same as [`Self::insert_bulk()`] but with a custom limit to the amount of
+  /// chunks awaiting their response at once.
+  pub async fn insert_bulk_with_concurrency<T: Serialize, I: IntoIterator<Item = T>>(
+    &mut self, table: &str, records: I, chunk_size: usize, concurrency: usize,
+  ) -> RpcResult<Vec<BulkInsertChunk>> {
+    bulk::insert_bulk(self, table, records, chunk_size, concurrency).await
+  }
+
+  /// Write a SurrealQL dump of the current database into the `writer`: the
+  /// definitions read with `INFO FOR DB` and `INFO FOR TABLE`, followed by the
+  /// statements that create the records of every table. The records are read
+  /// in pages of [`crate::dump::DEFAULT_EXPORT_PAGE_SIZE`] rows.
+  pub async fn export<W: AsyncWrite + Unpin>(&mut self, writer: W) -> DumpResult<()> {
+    dump::export(self, writer, dump::DEFAULT_EXPORT_PAGE_SIZE).await
+  }
+
+  /// Run the statements of a SurrealQL dump read from the `reader`, in
+  /// transactions of [`crate::dump::DEFAULT_IMPORT_CHUNK_SIZE`] statements.
+  pub async fn import<R: AsyncRead + Unpin>(&mut self, reader: R) -> DumpResult<()> {
+    self
+      .import_with_progress(reader, dump::DEFAULT_IMPORT_CHUNK_SIZE, |_| {})
+      .await
+  }
+
+  /// Same as [`Self::import()`] but with a custom amount of statements per
+  /// transaction, and the [ImportProgress] reported after every transaction.
+  ///
+  /// The transactions that were committed before a failing one are kept, the
+  /// [`DumpError::Import`](crate::dump::DumpError::Import) error tells how
+  /// many statements were imported.
+  pub async fn import_with_progress<R: AsyncRead + Unpin, F: FnMut(ImportProgress)>(
+    &mut self, reader: R, chunk_size: usize, on_progress: F,
+  ) -> DumpResult<()> {
+    dump::import(self, reader, chunk_size, on_progress).await
+  }
+
   /// Get the value for every row that were returned by the supplied `query` and
   /// where `key` exists. If the `key` is missing from a row then the row will
   /// be filtered out of the returned [Vec].
+  ///
+  /// The failed statements are returned as errors, like in [`Self::find_one()`].
   pub async fn find_many_key<T: DeserializeOwned>(
     &mut self, key: &str, query: String, params: Value,
   ) -> RpcResult<Vec<T>> {
-    let response = self.send_query(query, params).await?.await?;
+    let response = self.send_checked_query(query, params).await?;
 
     let values = response
       .get_nth_query_result(0)
-      .and_then(|query_results| Some(query_results.results_key(key)))
+      .map(|query_results| query_results.results_key(key))
       .unwrap_or_default();
 
     let deser_result: Vec<T> =
@@ -251,4 +736,66 @@ impl SurrealClient {
 
     Ok(deser_result)
   }
+
+  /// Create an `edge` going from the `from` record to the `to` record, with
+  /// the supplied `content`, and return the created edge.
+  pub async fn relate<E: Serialize + DeserializeOwned>(
+    &mut self, from: &RecordId, edge: &str, to: &RecordId, content: E,
+  ) -> RpcResult<Option<E>> {
+    let (query, mut params) = graph::relate_query(from, edge, to)?;
+    params["content"] = serde_json::to_value(content)?;
+
+    self.find_one(query, params).await
+  }
+
+  /// Get the `node` records that `from` points to through `edge`, that is the
+  /// records found with `from->edge->node`. The `fetch` fields of the nodes
+  /// are fetched as well.
+  pub async fn outgoing<T: DeserializeOwned>(
+    &mut self, from: &RecordId, edge: &str, node: &str, fetch: &[&str],
+  ) -> RpcResult<Vec<T>> {
+    let (query, params) =
+      graph::traversal_query(from, Direction::Outgoing, edge, Some(node), fetch)?;
+
+    self.find_traversed(query, params).await
+  }
+
+  /// Get the `node` records that point to `to` through `edge`, that is the
+  /// records found with `to<-edge<-node`. The `fetch` fields of the nodes are
+  /// fetched as well.
+  pub async fn incoming<T: DeserializeOwned>(
+    &mut self, to: &RecordId, edge: &str, node: &str, fetch: &[&str],
+  ) -> RpcResult<Vec<T>> {
+    let (query, params) = graph::traversal_query(to, Direction::Incoming, edge, Some(node), fetch)?;
+
+    self.find_traversed(query, params).await
+  }
+
+  /// Get the `edge` records going out of `from`.
+  pub async fn outgoing_edges<E: DeserializeOwned>(
+    &mut self, from: &RecordId, edge: &str,
+  ) -> RpcResult<Vec<E>> {
+    let (query, params) = graph::traversal_query(from, Direction::Outgoing, edge, None, &[])?;
+
+    self.find_traversed(query, params).await
+  }
+
+  /// Get the `edge` records coming into `to`.
+  pub async fn incoming_edges<E: DeserializeOwned>(
+    &mut self, to: &RecordId, edge: &str,
+  ) -> RpcResult<Vec<E>> {
+    let (query, params) = graph::traversal_query(to, Direction::Incoming, edge, None, &[])?;
+
+    self.find_traversed(query, params).await
+  }
+
+  async fn find_traversed<T: DeserializeOwned>(
+    &mut self, query: String, params: Value,
+  ) -> RpcResult<Vec<T>> {
+    let traversed = self
+      .find_one_key(graph::TRAVERSAL_KEY, query, params)
+      .await?;
+
+    Ok(traversed.unwrap_or_default())
+  }
 }
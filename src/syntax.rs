@@ -0,0 +1,454 @@
+use serde_json::Value;
+
+use crate::errors::SyntaxError;
+use crate::errors::SyntaxErrorKind;
+
+/// Parameters that are provided by SurrealDB itself and therefore never need
+/// a binding.
+const RESERVED_PARAMS: [&str; 11] = [
+  "auth", "session", "scope", "token", "this", "parent", "value", "before", "after", "event",
+  "input",
+];
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum TokenKind {
+  Param(String),
+  Word(String),
+  Open(char),
+  Close(char),
+  String,
+  Symbol(char),
+}
+
+#[derive(Debug)]
+pub(crate) struct Token {
+  pub kind: TokenKind,
+
+  /// The 1-based line and column of the first character of the token.
+  pub line: usize,
+  pub column: usize,
+
+  /// The byte offsets of the start and of the end of the token in the query.
+  pub start: usize,
+  pub end: usize,
+}
+
+/// A lightweight SurrealQL tokenizer, it only knows about what is needed to
+/// find the parameters and to check the query is well-formed: strings,
+/// comments, parameters and delimiters.
+struct Tokenizer<'a> {
+  query: &'a str,
+  chars: Vec<char>,
+  i: usize,
+  offset: usize,
+  line: usize,
+  column: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+  fn new(query: &'a str) -> Self {
+    Self {
+      query,
+      chars: query.chars().collect(),
+      i: 0,
+      offset: 0,
+      line: 1,
+      column: 1,
+    }
+  }
+
+  fn peek(&self, offset: usize) -> Option<char> {
+    self.chars.get(self.i + offset).copied()
+  }
+
+  fn bump(&mut self) -> Option<char> {
+    let c = self.peek(0)?;
+    self.i += 1;
+    self.offset += c.len_utf8();
+
+    match c {
+      '\n' => {
+        self.line += 1;
+        self.column = 1;
+      }
+      _ => self.column += 1,
+    };
+
+    Some(c)
+  }
+
+  fn bump_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> String {
+    let mut text = String::new();
+
+    while let Some(c) = self.peek(0).filter(|c| predicate(*c)) {
+      text.push(c);
+      self.bump();
+    }
+
+    text
+  }
+
+  /// Tokenize the whole query. The tokenizer recovers from the errors it
+  /// encounters, only the first one is returned.
+  fn run(mut self) -> (Vec<Token>, Option<SyntaxError>) {
+    let query = self.query;
+    let mut tokens = Vec::new();
+    let mut error = None;
+
+    while let Some(c) = self.peek(0) {
+      let (line, column, start) = (self.line, self.column, self.offset);
+      let mut error_at = |kind| {
+        error.get_or_insert_with(|| SyntaxError::new(kind, query, line, column));
+      };
+
+      let kind = match c {
+        '\'' | '"' | '`' => {
+          self.bump();
+
+          loop {
+            match self.bump() {
+              Some('\\') => {
+                self.bump();
+              }
+              Some(end) if end == c => break,
+              Some(_) => {}
+              None => {
+                error_at(SyntaxErrorKind::UnterminatedString);
+                break;
+              }
+            };
+          }
+
+          TokenKind::String
+        }
+        '#' => {
+          self.bump_while(|c| c != '\n');
+          continue;
+        }
+        '-' | '/' if self.peek(1) == Some(c) => {
+          self.bump_while(|c| c != '\n');
+          continue;
+        }
+        '/' if self.peek(1) == Some('*') => {
+          self.bump();
+          self.bump();
+
+          loop {
+            match self.bump() {
+              Some('*') if self.peek(0) == Some('/') => {
+                self.bump();
+                break;
+              }
+              Some(_) => {}
+              None => {
+                error_at(SyntaxErrorKind::UnterminatedComment);
+                break;
+              }
+            };
+          }
+
+          continue;
+        }
+        '$' => {
+          self.bump();
+
+          TokenKind::Param(self.bump_while(is_ident_char))
+        }
+        '(' | '[' | '{' => {
+          self.bump();
+
+          TokenKind::Open(c)
+        }
+        ')' | ']' | '}' => {
+          self.bump();
+
+          TokenKind::Close(c)
+        }
+        c if c.is_whitespace() => {
+          self.bump();
+          continue;
+        }
+        c if is_ident_char(c) => TokenKind::Word(self.bump_while(is_ident_char)),
+        c => {
+          self.bump();
+
+          TokenKind::Symbol(c)
+        }
+      };
+
+      tokens.push(Token {
+        kind,
+        line,
+        column,
+        start,
+        end: self.offset,
+      });
+    }
+
+    (tokens, error)
+  }
+}
+
+pub(crate) fn tokenize(query: &str) -> (Vec<Token>, Option<SyntaxError>) {
+  Tokenizer::new(query).run()
+}
+
+/// Split the `query` into its statements on the `;` that are not nested in a
+/// block, a string or a comment. The comments around the statements and the
+/// empty statements are left out.
+pub(crate) fn split_statements(query: &str) -> Vec<&str> {
+  let (mut statements, rest, _) = scan_statements(query);
+  statements.extend(rest);
+
+  statements
+}
+
+/// Same as [split_statements()] but only for the statements that are ended by
+/// a `;`, for a `query` that is read progressively. The byte offset right
+/// after the last of these `;` is returned along with them, the text after it
+/// may still be part of an incomplete statement.
+pub(crate) fn complete_statements(query: &str) -> (Vec<&str>, usize) {
+  let (statements, _, end) = scan_statements(query);
+
+  (statements, end)
+}
+
+/// Find the statements of the `query` that are ended by a `;`, the statement
+/// after the last `;` if there is one, and the byte offset right after that
+/// last `;`.
+fn scan_statements(query: &str) -> (Vec<&str>, Option<&str>, usize) {
+  let (tokens, _) = tokenize(query);
+  let mut statements = Vec::new();
+  let mut depth = 0_usize;
+  let mut statement: Option<(usize, usize)> = None;
+  let mut terminated_at = 0;
+
+  for token in &tokens {
+    match token.kind {
+      TokenKind::Symbol(';') if depth == 0 => {
+        if let Some((start, end)) = statement.take() {
+          statements.push(&query[start..end]);
+        }
+
+        terminated_at = token.end;
+        continue;
+      }
+      TokenKind::Open(_) => depth += 1,
+      TokenKind::Close(_) => depth = depth.saturating_sub(1),
+      _ => {}
+    };
+
+    let start = statement.map(|(start, _)| start).unwrap_or(token.start);
+    statement = Some((start, token.end));
+  }
+
+  let rest = statement.map(|(start, end)| &query[start..end]);
+
+  (statements, rest, terminated_at)
+}
+
+/// Get the `$params` the query expects to be bound, along with their token.
+/// The parameters SurrealDB provides and the parameters the query defines
+/// itself, with `LET` and `FOR` statements or as function arguments, are
+/// ignored.
+pub(crate) fn parameter_tokens(tokens: &[Token]) -> Vec<(&str, &Token)> {
+  let mut params = Vec::new();
+  let mut defined: Vec<&str> = Vec::new();
+
+  for (index, token) in tokens.iter().enumerate() {
+    let TokenKind::Param(name) = &token.kind else {
+      continue;
+    };
+
+    if name.is_empty() || RESERVED_PARAMS.contains(&name.to_lowercase().as_str()) {
+      continue;
+    }
+
+    let previous = index.checked_sub(1).map(|i| &tokens[i].kind);
+    let is_definition = match previous {
+      Some(TokenKind::Word(word)) => {
+        word.eq_ignore_ascii_case("let") || word.eq_ignore_ascii_case("for")
+      }
+      _ => false,
+    };
+
+    // the arguments of a function definition: `fn::name($arg: type)`
+    let is_argument = tokens.get(index + 1).map(|t| &t.kind) == Some(&TokenKind::Symbol(':'))
+      && tokens.get(index + 2).map(|t| &t.kind) != Some(&TokenKind::Symbol(':'));
+
+    if is_definition || is_argument {
+      defined.push(name);
+    } else if !defined.contains(&name.as_str()) {
+      params.push((name.as_str(), token));
+    }
+  }
+
+  params
+}
+
+/// Get the names of the `$params` the query expects to be bound, refer to
+/// [parameter_tokens].
+pub(crate) fn parameters(query: &str) -> Vec<String> {
+  let (tokens, _) = tokenize(query);
+
+  parameter_tokens(&tokens)
+    .into_iter()
+    .map(|(name, _)| name.to_owned())
+    .collect()
+}
+
+/// Run the client-side syntax checks on the `query`: the delimiters must be
+/// balanced, the strings and comments terminated, and every parameter must be
+/// found in the `params` object. Refer to
+/// [`SurrealClient::set_syntax_checks()`](crate::SurrealClient::set_syntax_checks).
+pub fn check_query(query: &str, params: &Value) -> Result<(), SyntaxError> {
+  let known_params: Vec<&str> = match params {
+    Value::Object(fields) => fields.keys().map(String::as_str).collect(),
+    _ => Vec::new(),
+  };
+
+  check(query, &known_params)
+}
+
+/// Check the `query` is well-formed and that its parameters are all found in
+/// `known_params`.
+fn check(query: &str, known_params: &[&str]) -> Result<(), SyntaxError> {
+  let (tokens, error) = tokenize(query);
+
+  if let Some(error) = error {
+    return Err(error);
+  }
+
+  let mut delimiters: Vec<&Token> = Vec::new();
+
+  for token in &tokens {
+    match token.kind {
+      TokenKind::Open(_) => delimiters.push(token),
+      TokenKind::Close(close) => {
+        let expected = delimiters.pop().map(|open| match open.kind {
+          TokenKind::Open('(') => ')',
+          TokenKind::Open('[') => ']',
+          _ => '}',
+        });
+
+        if expected != Some(close) {
+          return Err(SyntaxError::new(
+            SyntaxErrorKind::UnexpectedDelimiter {
+              found: close,
+              expected,
+            },
+            query,
+            token.line,
+            token.column,
+          ));
+        }
+      }
+      _ => {}
+    };
+  }
+
+  if let Some(open) = delimiters.pop() {
+    let TokenKind::Open(c) = open.kind else {
+      unreachable!()
+    };
+
+    return Err(SyntaxError::new(
+      SyntaxErrorKind::UnclosedDelimiter { open: c },
+      query,
+      open.line,
+      open.column,
+    ));
+  }
+
+  for (name, token) in parameter_tokens(&tokens) {
+    if !known_params.contains(&name) {
+      return Err(SyntaxError::new(
+        SyntaxErrorKind::UnknownParam {
+          name: name.to_owned(),
+        },
+        query,
+        token.line,
+        token.column,
+      ));
+    }
+  }
+
+  Ok(())
+}
+
+/// Whether one of the statements of the `query` is a `USE` statement, which
+/// switches the namespace or database of the session.
+pub(crate) fn has_use_statement(query: &str) -> bool {
+  let mentions_use = query
+    .as_bytes()
+    .windows(3)
+    .any(|window| window.eq_ignore_ascii_case(b"use"));
+
+  mentions_use
+    && split_statements(query).into_iter().any(|statement| {
+      statement
+        .split_whitespace()
+        .next()
+        .is_some_and(|keyword| keyword.eq_ignore_ascii_case("use"))
+    })
+}
+
+/// Get the single statement of the `query` without the comments around it,
+/// for the queries that are wrapped in another one. The queries made of more
+/// than one statement are rejected.
+pub(crate) fn single_statement(query: &str) -> Result<&str, SyntaxError> {
+  let (tokens, _) = tokenize(query);
+  let mut depth = 0_usize;
+  let mut statement: Option<(usize, usize)> = None;
+  let mut terminated = false;
+
+  for token in &tokens {
+    match token.kind {
+      TokenKind::Symbol(';') if depth == 0 => {
+        terminated = statement.is_some();
+        continue;
+      }
+      TokenKind::Open(_) => depth += 1,
+      TokenKind::Close(_) => depth = depth.saturating_sub(1),
+      _ => {}
+    };
+
+    if terminated {
+      return Err(SyntaxError::new(
+        SyntaxErrorKind::MultipleStatements,
+        query,
+        token.line,
+        token.column,
+      ));
+    }
+
+    let start = statement.map(|(start, _)| start).unwrap_or(token.start);
+    statement = Some((start, token.end));
+  }
+
+  Ok(
+    statement
+      .map(|(start, end)| &query[start..end])
+      .unwrap_or_default(),
+  )
+}
+
+/// Render the `line` of the `query` with a caret under the `column`, both are
+/// 1-based.
+pub(crate) fn caret_snippet(query: &str, line: usize, column: usize) -> String {
+  let source_line = query
+    .lines()
+    .nth(line.saturating_sub(1))
+    .unwrap_or_default();
+  let indentation: String = source_line
+    .chars()
+    .take(column.saturating_sub(1))
+    .map(|c| if c == '\t' { '\t' } else { ' ' })
+    .collect();
+
+  format!("{source_line}\n{indentation}^")
+}
+
+fn is_ident_char(c: char) -> bool {
+  c.is_alphanumeric() || c == '_'
+}
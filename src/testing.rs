@@ -0,0 +1,766 @@
+//! An in-process fake SurrealDB RPC server for tests, enabled by the `testing`
+//! feature.
+//!
+//! The [FakeServer] speaks the JSON RPC protocol over a local websocket, and
+//! the CBOR one if it is [enabled](FakeServerBuilder::cbor), with the
+//! messages [compressed](FakeServerBuilder::deflate) if asked to. It
+//! answers `version`, `signin`, `use`, `ping`, `let`, `unset` and `query` on
+//! its own, and the answers can be replaced with rules or scripted responses
+//! to inject latency, errors, disconnects and malformed frames:
+//! ```rust,ignore
+//! let server = FakeServer::builder()
+//!   .on_query("from account", FakeResponse::rows([json!({ "id": "account:one" })]))
+//!   .script([FakeResponse::Disconnect])
+//!   .latency(Duration::from_millis(10))
+//!   .start()
+//!   .await?;
+//!
+//! let mut client = SurrealClient::new(&server.url()).await?;
+//! ```
+//!
+//! The [TestDatabase] connects to a real instance instead, and isolates every
+//! test in a namespace of its own that is removed once the test is done:
+//! ```rust,ignore
+//! let mut db = TestDatabase::connect("ws://127.0.0.1:8000/rpc", "root", "root").await?;
+//! db.load_fixture("tests/fixtures/accounts.json").await?;
+//!
+//! let accounts: Vec<Account> = db.find_many("select * from account".to_owned(), json!({})).await?;
+//!
+//! db.cleanup().await?;
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ciborium::value::Value as CborValue;
+use futures::SinkExt;
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::Request;
+use tokio_tungstenite::tungstenite::handshake::server::Response;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::compression::CompressionCounters;
+use crate::compression::DeflateStream;
+use crate::compression::Role;
+use crate::dump::DumpError;
+use crate::protocol;
+use crate::rpc::RpcChannelError;
+use crate::rpc::RpcResult;
+use crate::syntax;
+use crate::Compression;
+use crate::Protocol;
+use crate::RecordId;
+use crate::SurrealClient;
+
+/// The version the [FakeServer] reports by default.
+pub const FAKE_SERVER_VERSION: &str = "surrealdb-1.4.2";
+
+/// A rule that decides the response to a request, [None] lets the next rules
+/// or the default behaviour answer it.
+pub type FakeRule = Box<dyn Fn(&FakeRequest) -> Option<FakeResponse> + Send + Sync>;
+
+/// A request received by the [FakeServer].
+#[derive(Debug, Clone)]
+pub struct FakeRequest {
+  pub id: String,
+  pub method: String,
+  pub params: Value,
+}
+
+impl FakeRequest {
+  /// The text of the query if this is a `query` request.
+  pub fn query(&self) -> Option<&str> {
+    match self.method.as_str() {
+      "query" => self.params.get(0).and_then(Value::as_str),
+      _ => None,
+    }
+  }
+}
+
+/// What the [FakeServer] does in response to a request.
+#[derive(Debug, Clone)]
+pub enum FakeResponse {
+  /// Answer with this value as the `result` of the response.
+  Result(Value),
+
+  /// Answer with this CBOR value as the `result` of the response, which can
+  /// hold the tagged values that JSON can't express. It is converted into
+  /// JSON on the connections that didn't negotiate CBOR.
+  Cbor(CborValue),
+
+  /// Answer with an RPC error.
+  Error { code: i32, message: String },
+
+  /// Wait before doing what the inner response says.
+  Delayed {
+    delay: Duration,
+    response: Box<FakeResponse>,
+  },
+
+  /// Close the connection without answering.
+  Disconnect,
+
+  /// Send this text frame as is, which is usually not a valid message.
+  Malformed(String),
+
+  /// Never answer.
+  Ignore,
+}
+
+impl FakeResponse {
+  /// The response to a query whose single statement returned the `rows`.
+  pub fn rows<I: IntoIterator<Item = Value>>(rows: I) -> Self {
+    let rows: Vec<Value> = rows.into_iter().collect();
+
+    Self::Result(json!([{ "time": "0ns", "status": "OK", "result": rows }]))
+  }
+
+  /// The response to a query whose single statement failed with `message`.
+  pub fn statement_error<M: Into<String>>(message: M) -> Self {
+    Self::Result(json!([{ "time": "0ns", "status": "ERR", "result": message.into() }]))
+  }
+
+  pub fn error<M: Into<String>>(code: i32, message: M) -> Self {
+    Self::Error {
+      code,
+      message: message.into(),
+    }
+  }
+
+  pub fn delayed(self, delay: Duration) -> Self {
+    Self::Delayed {
+      delay,
+      response: Box::new(self),
+    }
+  }
+}
+
+/// Configures a [FakeServer] before it is started, see
+/// [`FakeServer::builder()`].
+pub struct FakeServerBuilder {
+  version: String,
+  cbor: bool,
+  deflate: Option<Compression>,
+  credentials: Option<(String, String)>,
+  latency: Duration,
+  rules: Vec<FakeRule>,
+  script: Vec<FakeResponse>,
+}
+
+impl FakeServerBuilder {
+  /// The version returned by the `version` method.
+  pub fn version<V: Into<String>>(mut self, version: V) -> Self {
+    self.version = version.into();
+    self
+  }
+
+  /// Accept the CBOR protocol when the clients offer it, the connections use
+  /// JSON otherwise.
+  pub fn cbor(mut self) -> Self {
+    self.cbor = true;
+    self
+  }
+
+  /// Accept the `permessage-deflate` extension when the clients offer it, and
+  /// compress the responses as described by `compression`.
+  pub fn deflate(mut self, compression: Compression) -> Self {
+    self.deflate = Some(compression);
+    self
+  }
+
+  /// Only accept these credentials in `signin`, any credentials are accepted
+  /// otherwise.
+  pub fn credentials<U: Into<String>, P: Into<String>>(mut self, user: U, pass: P) -> Self {
+    self.credentials = Some((user.into(), pass.into()));
+    self
+  }
+
+  /// Wait before answering every request.
+  pub fn latency(mut self, latency: Duration) -> Self {
+    self.latency = latency;
+    self
+  }
+
+  /// Add a rule that can answer any request, the rules are tried in the order
+  /// they were added and before the scripted responses.
+  pub fn rule<F>(mut self, rule: F) -> Self
+  where
+    F: Fn(&FakeRequest) -> Option<FakeResponse> + Send + Sync + 'static,
+  {
+    self.rules.push(Box::new(rule));
+    self
+  }
+
+  /// Answer with `response` every query whose text contains `pattern`.
+  pub fn on_query<P: Into<String>>(self, pattern: P, response: FakeResponse) -> Self {
+    let pattern = pattern.into();
+
+    self.rule(move |request| {
+      request
+        .query()
+        .filter(|query| query.contains(&pattern))
+        .map(|_| response.clone())
+    })
+  }
+
+  /// Answer the queries the rules ignored with these responses, in order. Once
+  /// the script is exhausted the queries get their default response: an empty
+  /// result for every statement.
+  pub fn script<I: IntoIterator<Item = FakeResponse>>(mut self, responses: I) -> Self {
+    self.script.extend(responses);
+    self
+  }
+
+  pub async fn start(self) -> std::io::Result<FakeServer> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+    let state = Arc::new(FakeState {
+      version: self.version,
+      cbor: self.cbor,
+      deflate: self.deflate,
+      credentials: self.credentials,
+      latency: self.latency,
+      rules: self.rules,
+      script: Mutex::new(self.script.into()),
+      requests: Mutex::new(Vec::new()),
+    });
+
+    let accept_state = state.clone();
+    let accept_task = tokio::spawn(async move {
+      while let Ok((stream, _)) = listener.accept().await {
+        match accept_state.deflate {
+          Some(compression) => {
+            let counters = Arc::new(CompressionCounters::default());
+            let stream = DeflateStream::new(stream, Role::Server, compression, counters);
+
+            tokio::spawn(serve_connection(stream, accept_state.clone()))
+          }
+          None => tokio::spawn(serve_connection(stream, accept_state.clone())),
+        };
+      }
+    });
+
+    Ok(FakeServer {
+      address,
+      state,
+      accept_task,
+    })
+  }
+}
+
+/// An in-process SurrealDB RPC server, it stops accepting connections once
+/// dropped.
+pub struct FakeServer {
+  address: SocketAddr,
+  state: Arc<FakeState>,
+  accept_task: JoinHandle<()>,
+}
+
+impl FakeServer {
+  pub fn builder() -> FakeServerBuilder {
+    FakeServerBuilder {
+      version: FAKE_SERVER_VERSION.to_owned(),
+      cbor: false,
+      deflate: None,
+      credentials: None,
+      latency: Duration::ZERO,
+      rules: Vec::new(),
+      script: Vec::new(),
+    }
+  }
+
+  /// Start a server with the default behaviour.
+  pub async fn start() -> std::io::Result<Self> {
+    Self::builder().start().await
+  }
+
+  /// The url to pass to [`SurrealClient::new()`](crate::SurrealClient::new).
+  pub fn url(&self) -> String {
+    format!("ws://{}/rpc", self.address)
+  }
+
+  /// Every request received so far, from all the connections.
+  pub fn requests(&self) -> Vec<FakeRequest> {
+    self.state.requests.lock().unwrap().clone()
+  }
+
+  /// The text of every query received so far.
+  pub fn queries(&self) -> Vec<String> {
+    self
+      .requests()
+      .iter()
+      .filter_map(|request| request.query().map(str::to_owned))
+      .collect()
+  }
+}
+
+impl Drop for FakeServer {
+  fn drop(&mut self) {
+    self.accept_task.abort();
+  }
+}
+
+struct FakeState {
+  version: String,
+  cbor: bool,
+  deflate: Option<Compression>,
+  credentials: Option<(String, String)>,
+  latency: Duration,
+  rules: Vec<FakeRule>,
+  script: Mutex<VecDeque<FakeResponse>>,
+  requests: Mutex<Vec<FakeRequest>>,
+}
+
+impl FakeState {
+  fn respond(&self, request: &FakeRequest) -> FakeResponse {
+    if let Some(response) = self.rules.iter().find_map(|rule| rule(request)) {
+      return response;
+    }
+
+    match request.method.as_str() {
+      "version" => FakeResponse::Result(Value::from(self.version.as_str())),
+      "signin" => match &self.credentials {
+        Some((user, pass))
+          if request.params[0]["user"] != user.as_str()
+            || request.params[0]["pass"] != pass.as_str() =>
+        {
+          FakeResponse::error(-32000, "There was a problem with authentication")
+        }
+        _ => FakeResponse::Result(Value::Null),
+      },
+      "use" | "ping" | "let" | "set" | "unset" => FakeResponse::Result(Value::Null),
+      "query" => match self.script.lock().unwrap().pop_front() {
+        Some(response) => response,
+        None => {
+          let statements = syntax::split_statements(request.query().unwrap_or_default()).len();
+          let result = json!({ "time": "0ns", "status": "OK", "result": [] });
+
+          FakeResponse::Result(Value::Array(vec![result; statements.max(1)]))
+        }
+      },
+      method => FakeResponse::error(-32601, format!("Method not found: {method}")),
+    }
+  }
+}
+
+/// What the connection task does once a response was resolved.
+enum Outgoing {
+  Frame(Message),
+  Disconnect,
+}
+
+async fn serve_connection<S>(stream: S, state: Arc<FakeState>)
+where
+  S: AsyncRead + AsyncWrite + Unpin,
+{
+  let mut protocol = Protocol::Json;
+  // the error type of the callback is imposed by tungstenite
+  #[allow(clippy::result_large_err)]
+  let negotiate = |request: &Request, mut response: Response| {
+    let offers_cbor = request
+      .headers()
+      .get("Sec-WebSocket-Protocol")
+      .and_then(|value| value.to_str().ok())
+      .unwrap_or_default()
+      .split(',')
+      .any(|offer| Protocol::from_name(offer) == Some(Protocol::Cbor));
+
+    let offers_deflate = request
+      .headers()
+      .get("Sec-WebSocket-Extensions")
+      .and_then(|value| value.to_str().ok())
+      .is_some_and(|value| value.starts_with("permessage-deflate"));
+
+    // every response is compressed on its own, like the requests
+    if state.deflate.is_some() && offers_deflate {
+      response.headers_mut().insert(
+        "Sec-WebSocket-Extensions",
+        HeaderValue::from_static(
+          "permessage-deflate; client_no_context_takeover; server_no_context_takeover",
+        ),
+      );
+    }
+
+    if state.cbor && offers_cbor {
+      protocol = Protocol::Cbor;
+      response.headers_mut().insert(
+        "Sec-WebSocket-Protocol",
+        HeaderValue::from_static(Protocol::Cbor.name()),
+      );
+    }
+
+    Ok(response)
+  };
+
+  let Ok(socket) = tokio_tungstenite::accept_hdr_async(stream, negotiate).await else {
+    return;
+  };
+
+  let (mut sink, mut stream) = socket.split();
+  let (outgoing_sink, mut outgoing_stream) = mpsc::unbounded_channel();
+
+  loop {
+    tokio::select! {
+      frame = stream.next() => {
+        let Some(Ok(frame)) = frame else {
+          return;
+        };
+
+        let Some(request) = protocol::decode_frame(frame).and_then(parse_request) else {
+          continue;
+        };
+
+        state.requests.lock().unwrap().push(request.clone());
+
+        let response = state.respond(&request);
+        let latency = state.latency;
+        let outgoing_sink = outgoing_sink.clone();
+
+        // the requests are answered concurrently so a delayed response doesn't
+        // hold the next ones back
+        tokio::spawn(async move {
+          tokio::time::sleep(latency).await;
+
+          if let Some(outgoing) = resolve(request.id, response, protocol).await {
+            let _ = outgoing_sink.send(outgoing);
+          }
+        });
+      },
+
+      outgoing = outgoing_stream.recv() => match outgoing {
+        Some(Outgoing::Frame(frame)) => {
+          if sink.send(frame).await.is_err() {
+            return;
+          }
+        }
+        // dropping the socket closes the connection abruptly
+        Some(Outgoing::Disconnect) | None => return,
+      },
+    }
+  }
+}
+
+fn parse_request(value: Value) -> Option<FakeRequest> {
+  Some(FakeRequest {
+    id: value.get("id")?.as_str()?.to_owned(),
+    method: value.get("method")?.as_str()?.to_owned(),
+    params: value.get("params").cloned().unwrap_or_default(),
+  })
+}
+
+async fn resolve(id: String, mut response: FakeResponse, protocol: Protocol) -> Option<Outgoing> {
+  while let FakeResponse::Delayed {
+    delay,
+    response: inner,
+  } = response
+  {
+    tokio::time::sleep(delay).await;
+    response = *inner;
+  }
+
+  let frame = match response {
+    FakeResponse::Result(result) => encode(&json!({ "id": id, "result": result }), protocol),
+    FakeResponse::Cbor(result) if protocol == Protocol::Cbor => encode(
+      &CborValue::Map(vec![
+        (CborValue::Text("id".to_owned()), CborValue::Text(id)),
+        (CborValue::Text("result".to_owned()), result),
+      ]),
+      protocol,
+    ),
+    FakeResponse::Cbor(result) => encode(
+      &json!({ "id": id, "result": protocol::cbor_to_json(result) }),
+      protocol,
+    ),
+    FakeResponse::Error { code, message } => encode(
+      &json!({ "id": id, "error": { "code": code, "message": message } }),
+      protocol,
+    ),
+    FakeResponse::Malformed(text) => Message::Text(text),
+    FakeResponse::Disconnect => return Some(Outgoing::Disconnect),
+    FakeResponse::Ignore | FakeResponse::Delayed { .. } => return None,
+  };
+
+  Some(Outgoing::Frame(frame))
+}
+
+/// Encode a message in the frame of the `protocol` of the connection.
+fn encode<T: Serialize>(message: &T, protocol: Protocol) -> Message {
+  match protocol {
+    Protocol::Json => Message::Text(serde_json::to_string(message).unwrap_or_default()),
+    Protocol::Cbor => {
+      let mut bytes = Vec::new();
+      let _ = ciborium::ser::into_writer(message, &mut bytes);
+
+      Message::Binary(bytes)
+    }
+  }
+}
+
+#[derive(Debug, Error)]
+pub enum FixtureError {
+  Rpc {
+    inner: RpcChannelError,
+  },
+  Io {
+    path: PathBuf,
+    inner: std::io::Error,
+  },
+  Json {
+    path: PathBuf,
+    inner: serde_json::Error,
+  },
+  Dump {
+    path: PathBuf,
+    inner: Box<DumpError>,
+  },
+
+  /// The fixture is neither a `.surql` nor a `.json` file.
+  UnknownFormat {
+    path: PathBuf,
+  },
+}
+
+impl From<RpcChannelError> for FixtureError {
+  fn from(inner: RpcChannelError) -> Self {
+    Self::Rpc { inner }
+  }
+}
+
+impl Display for FixtureError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      FixtureError::Rpc { inner } => write!(f, "Fixture RPC error: {inner}"),
+      FixtureError::Io { path, inner } => {
+        write!(f, "Failed to read fixture {}: {inner}", path.display())
+      }
+      FixtureError::Json { path, inner } => {
+        write!(f, "Invalid JSON fixture {}: {inner}", path.display())
+      }
+      FixtureError::Dump { path, inner } => {
+        write!(f, "Failed to load fixture {}: {inner}", path.display())
+      }
+      FixtureError::UnknownFormat { path } => write!(
+        f,
+        "Unknown fixture format {}, expected a `.surql` or a `.json` file",
+        path.display()
+      ),
+    }
+  }
+}
+
+/// A client connected to a namespace and database of its own, so the tests
+/// that use it can't see nor disturb each other. The namespace is removed by
+/// [`Self::cleanup()`], or in the background when it is dropped without being
+/// cleaned up. The background removal may not be done before the process
+/// exits, so the last tests should await the cleanup.
+///
+/// It dereferences to the [SurrealClient] it wraps.
+pub struct TestDatabase {
+  client: SurrealClient,
+
+  /// The url and the credentials of the instance, the drop connects again to
+  /// remove the namespace.
+  url: String,
+  user: String,
+  pass: String,
+
+  namespace: String,
+  database: String,
+  removed: bool,
+}
+
+impl TestDatabase {
+  /// Connect to the instance at `url`, sign in, and use a namespace and a
+  /// database with unique names.
+  pub async fn connect<U: Into<String>, P: Into<String>>(
+    url: &str, user: U, pass: P,
+  ) -> RpcResult<Self> {
+    const ALPHABET: [char; 36] = [
+      '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h',
+      'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
+    ];
+
+    let (user, pass) = (user.into(), pass.into());
+    let mut client = SurrealClient::new(url).await?;
+    client.signin(&user, &pass).await?;
+
+    let namespace = format!("test_{}", nanoid::nanoid!(12, &ALPHABET));
+    let database = format!("test_{}", nanoid::nanoid!(12, &ALPHABET));
+    client
+      .use_namespace(namespace.as_str(), database.as_str())
+      .await?;
+
+    Ok(Self {
+      client,
+      url: url.to_owned(),
+      user,
+      pass,
+      namespace,
+      database,
+      removed: false,
+    })
+  }
+
+  pub fn namespace(&self) -> &str {
+    &self.namespace
+  }
+
+  pub fn database(&self) -> &str {
+    &self.database
+  }
+
+  /// Load a fixture into the database, depending on its extension:
+  /// - a `.surql` file is imported as a dump, see [`SurrealClient::import()`].
+  /// - a `.json` file holds an object whose fields are the names of the
+  ///   tables and whose values are the records to insert into them:
+  ///   `{ "account": [{ "id": "account:one", "name": "account one" }] }`
+  pub async fn load_fixture<P: AsRef<Path>>(&mut self, path: P) -> Result<(), FixtureError> {
+    let path = path.as_ref();
+    let io_error = |inner| FixtureError::Io {
+      path: path.to_owned(),
+      inner,
+    };
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+      Some("surql") => {
+        let file = tokio::fs::File::open(path).await.map_err(io_error)?;
+
+        self
+          .client
+          .import(file)
+          .await
+          .map_err(|inner| FixtureError::Dump {
+            path: path.to_owned(),
+            inner: Box::new(inner),
+          })
+      }
+      Some("json") => {
+        let content = tokio::fs::read_to_string(path).await.map_err(io_error)?;
+        let tables: Map<String, Value> =
+          serde_json::from_str(&content).map_err(|inner| FixtureError::Json {
+            path: path.to_owned(),
+            inner,
+          })?;
+
+        for (table, records) in tables {
+          self.insert_fixture_records(&table, records).await?;
+        }
+
+        Ok(())
+      }
+      _ => Err(FixtureError::UnknownFormat {
+        path: path.to_owned(),
+      }),
+    }
+  }
+
+  async fn insert_fixture_records(&mut self, table: &str, records: Value) -> RpcResult<()> {
+    let records: Vec<Value> = match records {
+      Value::Array(records) => records,
+      record => vec![record],
+    };
+
+    // the ids of the records are written as `table:id`, only the `id` part is
+    // kept so the records are created in the table rather than with an id
+    // that contains the name of the table.
+    let records = records.into_iter().map(|mut record| {
+      let id = record.get("id").and_then(Value::as_str);
+
+      if let Some(id) = id.and_then(|id| id.parse::<RecordId>().ok()) {
+        if id.table() == table {
+          record["id"] = id.id_param();
+        }
+      }
+
+      record
+    });
+
+    for chunk in self.client.insert_bulk(table, records, 1000).await? {
+      chunk.result?;
+    }
+
+    Ok(())
+  }
+
+  /// Remove the namespace of the test, along with everything it contains. The
+  /// drop tries again in the background if it fails.
+  pub async fn cleanup(mut self) -> RpcResult<()> {
+    self
+      .client
+      .send_checked_query(format!("REMOVE NAMESPACE {}", self.namespace), json!({}))
+      .await?;
+
+    self.removed = true;
+
+    Ok(())
+  }
+}
+
+impl Deref for TestDatabase {
+  type Target = SurrealClient;
+
+  fn deref(&self) -> &Self::Target {
+    &self.client
+  }
+}
+
+impl DerefMut for TestDatabase {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.client
+  }
+}
+
+impl Drop for TestDatabase {
+  /// Remove the namespace without waiting for it to be done, since blocking
+  /// would stall the worker of the runtime the drop happens on. The drop may
+  /// happen while the runtime of the test is shutting down, so the removal
+  /// runs on a thread and a runtime of its own, over a new connection.
+  fn drop(&mut self) {
+    if self.removed {
+      return;
+    }
+
+    let url = self.url.clone();
+    let credentials = (self.user.clone(), self.pass.clone());
+    let query = format!("REMOVE NAMESPACE {}", self.namespace);
+
+    std::thread::spawn(move || {
+      let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build();
+
+      if let Ok(runtime) = runtime {
+        let _ = runtime.block_on(remove_namespace(&url, credentials, query));
+      }
+    });
+  }
+}
+
+async fn remove_namespace(
+  url: &str, (user, pass): (String, String), query: String,
+) -> RpcResult<()> {
+  let mut client = SurrealClient::new(url).await?;
+  client.signin(user, pass).await?;
+  client.send_checked_query(query, json!({})).await?;
+
+  Ok(())
+}
@@ -0,0 +1,128 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::errors::ParseServerVersionError;
+
+/// The version of the SurrealDB instance the client is connected to, as it is
+/// returned by the `version` RPC method: `surrealdb-1.0.0-beta.9+20230402`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerVersion {
+  pub major: u64,
+  pub minor: u64,
+  pub patch: u64,
+
+  /// The pre-release identifiers, `beta.9` in `1.0.0-beta.9`
+  pub pre: Option<String>,
+}
+
+/// The oldest version the client supports.
+pub const MIN_SERVER_VERSION: ServerVersion = ServerVersion::new(1, 0, 0);
+
+/// The first major version the client doesn't support anymore.
+pub const UNSUPPORTED_SERVER_MAJOR: u64 = 3;
+
+impl ServerVersion {
+  pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+    Self {
+      major,
+      minor,
+      patch,
+      pre: None,
+    }
+  }
+
+  /// Whether the client can talk to this version of SurrealDB. The betas of
+  /// the first supported version are supported as well.
+  pub fn is_supported(&self) -> bool {
+    let release = Self::new(self.major, self.minor, self.patch);
+
+    release >= MIN_SERVER_VERSION && self.major < UNSUPPORTED_SERVER_MAJOR
+  }
+}
+
+impl FromStr for ServerVersion {
+  type Err = ParseServerVersionError;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let error = || ParseServerVersionError {
+      value: value.to_owned(),
+    };
+    let version = value.trim();
+    let version = version.strip_prefix("surrealdb-").unwrap_or(version);
+
+    // the build metadata is ignored
+    let version = version.split('+').next().unwrap_or_default();
+    let (release, pre) = match version.split_once('-') {
+      Some((release, pre)) => (release, Some(pre.to_owned())),
+      None => (version, None),
+    };
+
+    let mut numbers = release.split('.').map(|number| number.parse::<u64>());
+    let mut next = || numbers.next().ok_or_else(error)?.map_err(|_| error());
+
+    Ok(Self {
+      major: next()?,
+      minor: next()?,
+      patch: next()?,
+      pre,
+    })
+  }
+}
+
+impl Display for ServerVersion {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+
+    match &self.pre {
+      Some(pre) => write!(f, "-{pre}"),
+      None => Ok(()),
+    }
+  }
+}
+
+impl PartialOrd for ServerVersion {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+/// Versions are compared following semver: a pre-release comes before its
+/// release, and the numeric identifiers of the pre-releases are compared as
+/// numbers.
+impl Ord for ServerVersion {
+  fn cmp(&self, other: &Self) -> Ordering {
+    let release = (self.major, self.minor, self.patch);
+    let other_release = (other.major, other.minor, other.patch);
+
+    release
+      .cmp(&other_release)
+      .then_with(|| match (&self.pre, &other.pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(pre), Some(other_pre)) => compare_pre_releases(pre, other_pre),
+      })
+  }
+}
+
+fn compare_pre_releases(pre: &str, other_pre: &str) -> Ordering {
+  let mut identifiers = pre.split('.');
+  let mut other_identifiers = other_pre.split('.');
+
+  loop {
+    let ordering = match (identifiers.next(), other_identifiers.next()) {
+      (None, None) => return Ordering::Equal,
+      (None, Some(_)) => Ordering::Less,
+      (Some(_), None) => Ordering::Greater,
+      (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+      },
+    };
+
+    if ordering != Ordering::Equal {
+      return ordering;
+    }
+  }
+}
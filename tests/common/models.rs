@@ -3,10 +3,10 @@ use serde::Serialize;
 use serde_json::json;
 use serde_json::Value;
 use surreal_simple_client::rpc::RpcResult;
+use surreal_simple_client::LinkVec;
+use surreal_simple_client::RecordId;
 use surreal_simple_client::SurrealClient;
-use surreal_simple_querybuilder::prelude::ForeignVec;
 use surreal_simple_querybuilder::prelude::IntoKey;
-use surreal_simple_querybuilder::prelude::NodeBuilder;
 use surreal_simple_querybuilder::prelude::ToNodeBuilder;
 use surreal_simple_querybuilder::querybuilder::QueryBuilder;
 use surreal_simple_querybuilder::querybuilder::QueryBuilderSetObject;
@@ -18,7 +18,7 @@ pub struct User {
   pub name: String,
 
   #[serde(default)]
-  pub written_files: ForeignVec<File>,
+  pub written_files: LinkVec<File>,
 }
 
 pub mod user_schema {
@@ -41,8 +41,7 @@ impl IntoKey<String> for User {
   {
     self
       .id
-      .as_ref()
-      .map(String::clone)
+      .clone()
       .ok_or(serde::ser::Error::custom("The user has no ID"))
   }
 }
@@ -52,10 +51,11 @@ impl User {
     Self {
       id: None,
       name,
-      written_files: ForeignVec::Unloaded,
+      written_files: LinkVec::default(),
     }
   }
 
+  #[allow(clippy::wrong_self_convention)]
   pub fn into_json(&self) -> Value {
     serde_json::to_value(self).unwrap()
   }
@@ -75,13 +75,11 @@ impl User {
   pub async fn relate_with_file(
     client: &mut SurrealClient, user_id: &str, file_id: &str,
   ) -> RpcResult<()> {
+    let user_id: RecordId = user_id.parse().expect("invalid user id");
+    let file_id: RecordId = file_id.parse().expect("invalid file id");
+
     client
-      .send_query(
-        QueryBuilder::new()
-          .relate(user_id.with(user.written_files.name()).with(&file_id))
-          .build(),
-        json!({}),
-      )
+      .relate(&user_id, "write", &file_id, json!({}))
       .await?;
 
     Ok(())
@@ -102,7 +100,7 @@ impl User {
 }
 
 impl QueryBuilderSetObject for User {
-  fn set_querybuilder_object<'a>(mut querybuilder: QueryBuilder<'a>) -> QueryBuilder {
+  fn set_querybuilder_object<'a>(mut querybuilder: QueryBuilder<'a>) -> QueryBuilder<'a> {
     let a = &[querybuilder.hold(user.name.equals_parameterized())];
 
     querybuilder.set_many(a)
@@ -118,7 +116,7 @@ pub struct File {
   pub name: String,
 
   #[serde(default)]
-  pub authors: ForeignVec<User>,
+  pub authors: LinkVec<User>,
 }
 
 pub mod file_schema {
@@ -140,13 +138,13 @@ impl IntoKey<String> for File {
   {
     self
       .id
-      .as_ref()
-      .map(String::clone)
+      .clone()
       .ok_or(serde::ser::Error::custom("The file has no ID"))
   }
 }
 
 impl File {
+  #[allow(clippy::wrong_self_convention)]
   pub fn into_json(&self) -> Value {
     serde_json::to_value(self).unwrap()
   }
@@ -155,7 +153,7 @@ impl File {
     Self {
       name,
       id: None,
-      authors: ForeignVec::Unloaded,
+      authors: LinkVec::default(),
     }
   }
 
@@ -173,7 +171,7 @@ impl File {
 }
 
 impl QueryBuilderSetObject for File {
-  fn set_querybuilder_object<'a>(mut querybuilder: QueryBuilder<'a>) -> QueryBuilder {
+  fn set_querybuilder_object<'a>(mut querybuilder: QueryBuilder<'a>) -> QueryBuilder<'a> {
     let a = &[querybuilder.hold(user.name.equals_parameterized())];
 
     querybuilder.set_many(a)
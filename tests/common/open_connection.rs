@@ -1,23 +1,16 @@
-use nanoid::nanoid;
 use surreal_simple_client::rpc::RpcResult;
-use surreal_simple_client::SurrealClient;
+use surreal_simple_client::testing::TestDatabase;
 
 use super::prepare_data;
 
-pub async fn open_connection() -> RpcResult<SurrealClient> {
-  let mut client = SurrealClient::new("ws://127.0.0.1:8000/rpc")
-    .await
-    .expect("RPC handshake error");
-
-  client.signin("root", "root").await.expect("Signin error");
-  client
-    .use_namespace(nanoid!(), nanoid!())
-    .await
-    .expect("Namespace error");
+/// Every test gets a namespace of its own, so they start from an empty database
+/// and the namespace is removed once the test is done.
+pub async fn open_connection() -> RpcResult<TestDatabase> {
+  let mut db = TestDatabase::connect("ws://127.0.0.1:8000/rpc", "root", "root").await?;
 
   // every time we open a new connection for the test we prepare the data, flush
   // everything we may not want and add additional data we may need.
-  prepare_data(&mut client).await?;
+  prepare_data(&mut db).await?;
 
-  Ok(client)
+  Ok(db)
 }
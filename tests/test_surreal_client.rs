@@ -5,15 +5,50 @@ mod common;
 
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 
+use ciborium::value::Value as CborValue;
 use common::models::File;
 use common::models::User;
 use common::open_connection;
+use futures::StreamExt;
+use serde::Deserialize;
 use serde_json::json;
+use serde_json::Value;
+use surreal_simple_client::check_query;
+use surreal_simple_client::dump::DumpError;
+use surreal_simple_client::errors::StatementError;
+use surreal_simple_client::errors::SyntaxErrorKind;
+use surreal_simple_client::migrations::Migration;
+use surreal_simple_client::migrations::MigrationError;
+use surreal_simple_client::migrations::Migrator;
+use surreal_simple_client::rpc::RpcChannelError;
 use surreal_simple_client::rpc::RpcResult;
+use surreal_simple_client::schema::DefineEvent;
+use surreal_simple_client::schema::DefineField;
+use surreal_simple_client::schema::DefineFunction;
+use surreal_simple_client::schema::DefineIndex;
+use surreal_simple_client::schema::DefineTable;
+use surreal_simple_client::schema::PermissionAction;
+use surreal_simple_client::schema::Permissions;
+use surreal_simple_client::schema::Schema;
+use surreal_simple_client::testing::FakeResponse;
+use surreal_simple_client::testing::FakeServer;
+use surreal_simple_client::testing::FixtureError;
+use surreal_simple_client::testing::TestDatabase;
+use surreal_simple_client::testing::FAKE_SERVER_VERSION;
+use surreal_simple_client::Compression;
+use surreal_simple_client::FlowControl;
+use surreal_simple_client::Link;
+use surreal_simple_client::Protocol;
+use surreal_simple_client::RecordId;
+use surreal_simple_client::RetryPolicy;
+use surreal_simple_client::ServerVersion;
 use surreal_simple_client::SurrealClient;
+use surreal_simple_client::SurrealPool;
+use tokio::io::AsyncWriteExt;
 
-const USER0_NAME: &'static str = "User0";
+const USER0_NAME: &str = "User0";
 
 #[tokio::test]
 async fn it_connects() {
@@ -117,7 +152,7 @@ async fn it_retrieves_one_key() -> RpcResult<()> {
     )
     .await?;
 
-  let files_length = files.and_then(|v| Some(v.len())).unwrap_or(0);
+  let files_length = files.map(|v| v.len()).unwrap_or(0);
 
   assert_eq!(1, files_length);
 
@@ -167,3 +202,2070 @@ async fn it_retrieves_many_key() -> RpcResult<()> {
 
   Ok(())
 }
+
+/// This test confirms the `Client::query` builder serializes the bound values
+/// and sends them along with the query.
+#[tokio::test]
+async fn it_binds_query_parameters() -> RpcResult<()> {
+  let mut client = open_connection().await?;
+
+  it_creates_data(&mut client).await?;
+
+  let user: Option<User> = client
+    .query("select * from User where name = $name")
+    .bind(("name", USER0_NAME))
+    .find_one()
+    .await?;
+
+  assert_eq!(Some(USER0_NAME.to_owned()), user.map(|user| user.name));
+
+  let files: Vec<File> = client
+    .query("let $names = $file_names; select * from File where name inside $names")
+    .bind(("file_names", ["LoremIpsum"]))
+    .take(1)
+    .await?;
+
+  assert_eq!(1, files.len());
+
+  Ok(())
+}
+
+/// This test confirms a query is not sent if one of its parameters is missing
+/// a binding.
+#[tokio::test]
+async fn it_rejects_missing_bindings() -> RpcResult<()> {
+  let mut client = open_connection().await?;
+
+  let result = client
+    .query("select * from User where name = $name and id != $id -- $comment")
+    .bind(("name", USER0_NAME))
+    .find_many::<User>()
+    .await;
+
+  assert!(matches!(
+    result,
+    Err(RpcChannelError::MissingBinding { name }) if name == "id"
+  ));
+
+  Ok(())
+}
+
+/// This test confirms the `(name, value)` tuples of any serializable value are
+/// bound to the parameters of the query.
+#[tokio::test]
+async fn it_binds_tuples_of_any_serializable_value() -> RpcResult<()> {
+  let server = FakeServer::start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  let name = String::from(USER0_NAME);
+  let query = client
+    .query("select * from User where name = $name and age > $age and id in $ids")
+    .bind(("name", &name))
+    .bind(("age", 18))
+    .bind((String::from("ids"), vec!["User:one", "User:two"]));
+
+  assert!(query.missing_bindings().is_empty());
+  assert_eq!(
+    &json!({ "name": USER0_NAME, "age": 18, "ids": ["User:one", "User:two"] }),
+    &Value::Object(query.bindings().clone())
+  );
+
+  Ok(())
+}
+
+/// This test confirms the `Client::stream` function yields every row of the
+/// query even when they span over multiple pages.
+#[tokio::test]
+async fn it_streams_rows_in_pages() -> RpcResult<()> {
+  let mut client = open_connection().await?;
+
+  client
+    .send_query(
+      "
+      create file:one set name = 'file one';
+      create file:two set name = 'file two';
+      create file:three set name = 'file three';
+      create file:four set name = 'file four';
+      create file:five set name = 'file five';
+    "
+      .to_owned(),
+      json!({}),
+    )
+    .await?
+    .await?;
+
+  let files: Vec<RpcResult<File>> = client
+    .stream("select * from file order by name;".to_owned(), json!({}), 2)
+    .collect()
+    .await;
+
+  assert_eq!(5, files.len());
+  assert!(files.iter().all(|file| file.is_ok()));
+
+  Ok(())
+}
+
+/// This test confirms a stream ends after its first error, and that the
+/// queries made of more than one statement are rejected before being sent.
+#[tokio::test]
+async fn it_ends_the_streams_after_an_error() -> RpcResult<()> {
+  let server = FakeServer::builder()
+    .on_query(
+      "from file",
+      FakeResponse::rows([
+        json!({ "name": "file one" }),
+        json!({ "name": 2 }),
+        json!({ "name": "file three" }),
+      ]),
+    )
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+
+  let files: Vec<RpcResult<File>> = client
+    .stream("select * from file".to_owned(), json!({}), 3)
+    .collect()
+    .await;
+
+  assert_eq!(2, files.len());
+  assert!(files[0].is_ok());
+  assert!(matches!(
+    files[1],
+    Err(RpcChannelError::SurrealBodyParsingError { .. })
+  ));
+
+  let sent = server.queries().len();
+  let files: Vec<RpcResult<File>> = client
+    .stream("delete file; select * from file;".to_owned(), json!({}), 3)
+    .collect()
+    .await;
+
+  assert_eq!(1, files.len());
+  assert!(matches!(
+    &files[0],
+    Err(RpcChannelError::SyntaxError { inner })
+      if inner.kind == SyntaxErrorKind::MultipleStatements && inner.column == 14
+  ));
+  assert_eq!(sent, server.queries().len());
+
+  Ok(())
+}
+
+/// This test confirms the paginated queries are wrapped in the query that
+/// pages them, so their own clauses, their fields named like clauses and their
+/// trailing comments are left as they are.
+#[tokio::test]
+async fn it_wraps_the_paginated_queries() -> RpcResult<()> {
+  let server = FakeServer::builder()
+    .rule(|request| {
+      let rows = match request.params[1]["__page_start"].as_u64()? {
+        0 => vec![json!({ "start": 1 }), json!({ "start": 2 })],
+        _ => vec![json!({ "start": 3 })],
+      };
+
+      Some(FakeResponse::rows(rows))
+    })
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  let queries = [
+    "select * from event -- every event",
+    "select * from event where start > $t order by timeout limit 10 fetch author;",
+  ];
+
+  for query in queries {
+    let events: Vec<Value> = client
+      .stream(query.to_owned(), json!({ "t": 0 }), 2)
+      .map(|event| event.expect("Failed to read an event"))
+      .collect()
+      .await;
+
+    assert_eq!(3, events.len());
+  }
+
+  assert_eq!(
+    vec![
+      "SELECT * FROM (\nselect * from event\n) LIMIT $__page_limit START $__page_start",
+      "SELECT * FROM (\nselect * from event\n) LIMIT $__page_limit START $__page_start",
+      "SELECT * FROM (\nselect * from event where start > $t order by timeout limit 10 fetch author\n) LIMIT $__page_limit START $__page_start",
+      "SELECT * FROM (\nselect * from event where start > $t order by timeout limit 10 fetch author\n) LIMIT $__page_limit START $__page_start",
+    ],
+    server.queries()
+  );
+
+  Ok(())
+}
+
+/// This test confirms the `Client::insert_bulk` function inserts every record
+/// and reports the ids created by each chunk.
+#[tokio::test]
+async fn it_inserts_in_bulk() -> RpcResult<()> {
+  let mut client = open_connection().await?;
+
+  let files = (0..25).map(|i| File::new(format!("file {i}")));
+  let chunks = client.insert_bulk("file", files, 10).await?;
+
+  assert_eq!(3, chunks.len());
+  assert!(chunks.iter().all(|chunk| chunk.is_ok()));
+
+  let created: usize = chunks
+    .iter()
+    .filter_map(|chunk| chunk.result.as_ref().ok())
+    .map(|ids| ids.len())
+    .sum();
+
+  assert_eq!(25, created);
+
+  let files: Vec<File> = client
+    .find_many("select * from file".to_owned(), json!({}))
+    .await?;
+
+  assert_eq!(25, files.len());
+
+  Ok(())
+}
+
+/// This test confirms the migrations are loaded from the `.surql` files of a
+/// directory, in order of version and with their down migrations.
+#[test]
+fn it_loads_migrations_from_a_directory() {
+  let migrator = Migrator::from_directory("tests/migrations").expect("Failed to load migrations");
+  let versions: Vec<u64> = migrator.migrations().iter().map(|m| m.version).collect();
+
+  assert_eq!(vec![1, 2], versions);
+  assert!(migrator.migrations().iter().all(|m| m.down.is_some()));
+}
+
+/// This test confirms the `Migrator` applies the pending migrations once, can
+/// revert them, and detects a migration that was edited after being applied.
+#[tokio::test]
+async fn it_applies_migrations() -> Result<(), MigrationError> {
+  let mut client = open_connection().await?;
+  let migrator = Migrator::from_directory("tests/migrations")?;
+
+  let planned = migrator.clone().dry_run(true).up(&mut client).await?;
+  assert_eq!(vec![1, 2], planned);
+  assert!(migrator.applied(&mut client).await?.is_empty());
+
+  assert_eq!(vec![1, 2], migrator.up(&mut client).await?);
+  assert!(migrator.up(&mut client).await?.is_empty());
+
+  let mut edited = migrator.migrations().to_vec();
+  edited[1] = Migration::new(
+    2,
+    "account_name_index",
+    "DEFINE INDEX other ON account FIELDS name;",
+  );
+
+  assert!(matches!(
+    Migrator::new(edited)?.up(&mut client).await,
+    Err(MigrationError::ChecksumMismatch { version: 2, .. })
+  ));
+
+  assert_eq!(vec![2, 1], migrator.down(&mut client, 0).await?);
+  assert!(migrator.applied(&mut client).await?.is_empty());
+
+  Ok(())
+}
+
+/// This test confirms the migrations that begin, commit or cancel their own
+/// transaction are rejected since they are already applied in a transaction.
+#[test]
+fn it_rejects_transaction_statements_in_migrations() {
+  let migrations = vec![Migration::new(
+    1,
+    "account",
+    "BEGIN TRANSACTION;\nDEFINE TABLE account SCHEMAFULL;\nCOMMIT TRANSACTION;",
+  )];
+
+  assert!(matches!(
+    Migrator::new(migrations),
+    Err(MigrationError::TransactionStatement { version: 1 })
+  ));
+
+  let migrations = vec![
+    Migration::new(1, "account", "DEFINE TABLE account SCHEMAFULL;"),
+    Migration::new(
+      2,
+      "account_name",
+      "DEFINE FIELD name ON account TYPE string;",
+    )
+    .with_down("REMOVE FIELD name ON account;\nCANCEL TRANSACTION"),
+  ];
+
+  assert!(matches!(
+    Migrator::new(migrations),
+    Err(MigrationError::TransactionStatement { version: 2 })
+  ));
+}
+
+/// This test confirms the migrations are applied while holding the lock record,
+/// and that a runner doesn't apply anything while another one holds it.
+#[tokio::test]
+async fn it_locks_the_migrations() -> Result<(), MigrationError> {
+  let migrator = Migrator::new(vec![Migration::new(
+    1,
+    "account",
+    "DEFINE TABLE account SCHEMAFULL -- the accounts of the users",
+  )])?;
+
+  let server = FakeServer::start()
+    .await
+    .expect("Failed to start the fake server");
+  let mut client = SurrealClient::new(&server.url()).await?;
+
+  assert_eq!(vec![1], migrator.up(&mut client).await?);
+
+  let queries = server.queries();
+  assert_eq!(4, queries.len());
+  assert!(queries[0].starts_with("CREATE type::thing($table, $id)"));
+  assert!(queries[2].contains("DEFINE TABLE account SCHEMAFULL;\nCREATE"));
+  assert!(queries[3].starts_with("DELETE type::thing($table, $id) WHERE owner = $owner"));
+
+  let locked_server = FakeServer::builder()
+    .on_query(
+      "CREATE type::thing($table, $id) CONTENT { owner",
+      FakeResponse::statement_error("Database record `_migrations_lock:lock` already exists"),
+    )
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+  let mut client = SurrealClient::new(&locked_server.url()).await?;
+
+  assert!(matches!(
+    migrator.up(&mut client).await,
+    Err(MigrationError::Locked)
+  ));
+  assert_eq!(1, locked_server.queries().len());
+
+  Ok(())
+}
+
+fn account_schema() -> Schema {
+  Schema::new()
+    .define(
+      DefineTable::new("account")
+        .schemafull()
+        .permissions(Permissions::For(vec![
+          (PermissionAction::Select, "FULL".to_owned()),
+          (PermissionAction::Delete, "NONE".to_owned()),
+        ])),
+    )
+    .define(
+      DefineField::new("email", "account")
+        .kind("string")
+        .assert("string::is::email($value)"),
+    )
+    .define(DefineIndex::new("account_email", "account", ["email"]).unique())
+}
+
+/// This test confirms the schema definitions render to the expected
+/// SurrealQL statements.
+#[test]
+fn it_renders_schema_definitions() {
+  let schema = account_schema().define(
+    DefineFunction::new("fn::greet")
+      .arg("name", "string")
+      .body("RETURN 'Hello ' + $name;"),
+  );
+
+  assert_eq!(
+    "DEFINE TABLE account SCHEMAFULL PERMISSIONS FOR select FULL FOR delete NONE;\n\
+     DEFINE FIELD email ON account TYPE string ASSERT string::is::email($value);\n\
+     DEFINE INDEX account_email ON account FIELDS email UNIQUE;\n\
+     DEFINE FUNCTION fn::greet($name: string) { RETURN 'Hello ' + $name; };\n",
+    schema.to_surql()
+  );
+
+  let event = DefineEvent::new("signup", "account", "CREATE log SET account = $after.id")
+    .when("$event = 'CREATE'");
+
+  assert_eq!(
+    "DEFINE EVENT signup ON TABLE account WHEN $event = 'CREATE' THEN (CREATE log SET account = $after.id)",
+    event.to_string()
+  );
+}
+
+/// This test confirms a schema that was applied is found in the database, and
+/// that the definitions missing from the database are reported.
+#[tokio::test]
+async fn it_diffs_the_schema() -> RpcResult<()> {
+  let mut client = open_connection().await?;
+  let schema = account_schema();
+
+  let diff = schema.diff(&mut client).await?;
+  assert_eq!(3, diff.missing.len());
+
+  schema.apply(&mut client).await?;
+
+  let diff = schema.diff(&mut client).await?;
+  assert!(diff.is_empty(), "{diff:?}");
+
+  Ok(())
+}
+
+/// This test confirms the definitions are compared with the statements as
+/// SurrealDB outputs them, where the implied clauses are explicit and the
+/// permissions are grouped by rule.
+#[tokio::test]
+async fn it_diffs_the_schema_with_the_canonical_definitions() -> RpcResult<()> {
+  let info = |result: Value| {
+    FakeResponse::Result(json!([{ "time": "0ns", "status": "OK", "result": result }]))
+  };
+  let server = FakeServer::builder()
+    .on_query(
+      "INFO FOR DB",
+      info(json!({
+        "tb": {
+          "account": "DEFINE TABLE account TYPE ANY SCHEMAFULL PERMISSIONS FOR select, create, update FULL, FOR delete NONE",
+          "file": "DEFINE TABLE file TYPE NORMAL SCHEMALESS PERMISSIONS NONE"
+        }
+      })),
+    )
+    .on_query(
+      "INFO FOR TABLE account",
+      info(json!({
+        "fd": {
+          "email": "DEFINE FIELD email ON account TYPE string ASSERT string::is::email($value) PERMISSIONS FULL"
+        },
+        "ix": {
+          "account_email": "DEFINE INDEX account_email ON account FIELDS email UNIQUE"
+        }
+      })),
+    )
+    .on_query(
+      "INFO FOR TABLE file",
+      info(json!({
+        "fd": {
+          "name": "DEFINE FIELD name ON file TYPE string PERMISSIONS FOR select, create, update, delete WHERE $auth.admin = true"
+        }
+      })),
+    )
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+
+  let diff = account_schema()
+    .define(DefineTable::new("file"))
+    .define(
+      DefineField::new("name", "file")
+        .kind("string")
+        .permissions(Permissions::For(vec![
+          (
+            PermissionAction::Select,
+            "WHERE $auth.admin = true".to_owned(),
+          ),
+          (
+            PermissionAction::Create,
+            "WHERE $auth.admin = true".to_owned(),
+          ),
+          (
+            PermissionAction::Update,
+            "WHERE $auth.admin = true".to_owned(),
+          ),
+          (
+            PermissionAction::Delete,
+            "WHERE $auth.admin = true".to_owned(),
+          ),
+        ])),
+    )
+    .diff(&mut client)
+    .await?;
+
+  assert!(diff.is_empty(), "{diff:?}");
+
+  let diff = account_schema()
+    .define(DefineTable::new("file").permissions(Permissions::Full))
+    .define(DefineField::new("name", "file").kind("string"))
+    .diff(&mut client)
+    .await?;
+
+  let changed: Vec<String> = diff
+    .changed
+    .iter()
+    .map(|(definition, _)| definition.key().name)
+    .collect();
+
+  assert_eq!(vec!["file", "name"], changed);
+
+  Ok(())
+}
+
+/// This test confirms the tables are escaped when their definitions are read,
+/// and that the case of the strings matters when the definitions are compared
+/// while the case of the keywords doesn't.
+#[tokio::test]
+async fn it_diffs_the_strings_with_their_case() -> RpcResult<()> {
+  let info = |result: Value| {
+    FakeResponse::Result(json!([{ "time": "0ns", "status": "OK", "result": result }]))
+  };
+  let server = FakeServer::builder()
+    .on_query(
+      "INFO FOR DB",
+      info(json!({
+        "tb": { "audit-log": "DEFINE TABLE `audit-log` SCHEMALESS PERMISSIONS NONE" }
+      })),
+    )
+    .on_query(
+      "INFO FOR TABLE `audit-log`",
+      info(json!({
+        "ev": {
+          "admin_login": "DEFINE EVENT admin_login ON `audit-log` WHEN $after.role = 'Admin' THEN (CREATE alert SET level = 'High')"
+        }
+      })),
+    )
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  let schema = |role: &str| {
+    Schema::new().define(DefineTable::new("audit-log")).define(
+      DefineEvent::new(
+        "admin_login",
+        "audit-log",
+        "create alert set level = 'High'",
+      )
+      .when(format!("$after.role = '{role}'")),
+    )
+  };
+
+  let diff = schema("Admin").diff(&mut client).await?;
+  assert!(diff.is_empty(), "{diff:?}");
+
+  let diff = schema("admin").diff(&mut client).await?;
+  assert_eq!(1, diff.changed.len(), "{diff:?}");
+
+  assert!(server
+    .queries()
+    .iter()
+    .any(|query| query == "INFO FOR TABLE `audit-log`"));
+
+  Ok(())
+}
+
+/// This test confirms the errors reported by the database are mapped to the
+/// HTTP status codes the web framework integrations respond with.
+#[test]
+fn it_maps_errors_to_http_status_codes() {
+  let unique_violation = RpcChannelError::SurrealStatementError {
+    inner: StatementError::from_message(
+      "Database index `email` already contains 'john@doe.com', with record `account:one`",
+    ),
+  };
+  let missing_binding = RpcChannelError::MissingBinding {
+    name: "name".to_owned(),
+  };
+
+  assert_eq!(409, unique_violation.http_status_code());
+  assert_eq!(500, missing_binding.http_status_code());
+}
+
+#[cfg(feature = "axum")]
+#[test]
+fn it_converts_errors_into_axum_responses() {
+  use axum::response::IntoResponse;
+
+  let error = RpcChannelError::SurrealStatementError {
+    inner: StatementError::from_message("The record `account:one` does not exist"),
+  };
+
+  assert_eq!(404, error.into_response().status().as_u16());
+}
+
+/// This test confirms the problem details of an error hide its full message
+/// unless exposing it was explicitly enabled.
+#[test]
+fn it_builds_problem_details() {
+  let error = RpcChannelError::SurrealStatementError {
+    inner: StatementError::from_message("Database index `email` already contains 'john@doe.com'"),
+  };
+
+  let details = error.problem_details();
+  assert_eq!(409, details["status"]);
+  assert_eq!(error.public_message(), details["detail"]);
+}
+
+#[cfg(feature = "actix")]
+#[test]
+fn it_converts_errors_into_actix_responses() {
+  use actix_web::ResponseError;
+
+  let error = RpcChannelError::SurrealStatementError {
+    inner: StatementError::from_message("The record `account:one` does not exist"),
+  };
+  let response = error.error_response();
+
+  assert_eq!(404, response.status().as_u16());
+  assert_eq!(
+    "application/problem+json",
+    response.headers().get("content-type").unwrap()
+  );
+}
+
+/// This test confirms the delay between two retries grows exponentially
+/// without exceeding the maximum backoff.
+#[test]
+fn it_computes_retry_backoffs() {
+  let policy = RetryPolicy::exponential(10);
+
+  assert_eq!(Duration::from_millis(50), policy.backoff(0));
+  assert_eq!(Duration::from_millis(200), policy.backoff(2));
+  assert_eq!(Duration::from_secs(2), policy.backoff(9));
+}
+
+/// This test confirms only the errors caused by temporary conditions are
+/// considered as transient.
+#[test]
+fn it_classifies_transient_errors() {
+  let conflict = RpcChannelError::SurrealStatementError {
+    inner: StatementError::from_message(
+      "Failed to commit transaction due to a read or write conflict. This transaction can be retried",
+    ),
+  };
+  let timeout = RpcChannelError::Timeout {
+    duration: Duration::from_secs(1),
+  };
+  let unique_violation = RpcChannelError::SurrealStatementError {
+    inner: StatementError::from_message("Database index `email` already contains 'john@doe.com'"),
+  };
+
+  assert!(conflict.is_transient());
+  assert!(timeout.is_transient());
+  assert!(!unique_violation.is_transient());
+}
+
+/// This test confirms the messages of the failed statements are parsed into
+/// the matching `StatementError`.
+#[test]
+fn it_parses_statement_errors() {
+  let unique_violation = StatementError::from_message(
+    "Database index `email` already contains 'john@doe.com', with record `account:one`",
+  );
+
+  assert!(matches!(
+    unique_violation,
+    StatementError::UniqueViolation { index, value, record, .. }
+      if index == "email" && value == "john@doe.com" && record.as_deref() == Some("account:one")
+  ));
+
+  let assertion = StatementError::from_message(
+    "Found 'john' for field `email`, with record `account:one`, but field must conform to: string::is::email($value)",
+  );
+
+  assert!(matches!(
+    assertion,
+    StatementError::FieldAssertion { field, value, assertion, .. }
+      if field == "email" && value == "john" && assertion == "string::is::email($value)"
+  ));
+
+  let parse = StatementError::from_message(
+    "Parse error on line 2 at character 14 when parsing 'SELEC * FROM account'",
+  );
+
+  assert!(matches!(
+    parse,
+    StatementError::Parse {
+      line: Some(2),
+      column: Some(15),
+      ..
+    }
+  ));
+
+  assert!(matches!(
+    StatementError::from_message("You don't have permission to perform this query type"),
+    StatementError::PermissionDenied { .. }
+  ));
+  assert!(matches!(
+    StatementError::from_message("Found record: `account:one` which is not a relation"),
+    StatementError::NotARelation { record: Some(record), .. } if record == "account:one"
+  ));
+  assert!(matches!(
+    StatementError::from_message("The record `account:one` does not exist"),
+    StatementError::RecordNotFound { record: Some(record), .. } if record == "account:one"
+  ));
+
+  for message in [
+    "The table `account` does not exist",
+    "The namespace `test` does not exist",
+    "Method not found",
+  ] {
+    assert!(matches!(
+      StatementError::from_message(message),
+      StatementError::Other { .. }
+    ));
+  }
+}
+
+/// This test confirms a failing statement is reported as a parsed
+/// `StatementError` by the client.
+#[tokio::test]
+async fn it_reports_statement_errors() -> RpcResult<()> {
+  let mut client = open_connection().await?;
+
+  client
+    .send_query(
+      "
+      define index account_name on account fields name unique;
+      create account:one set name = 'account';
+    "
+      .to_owned(),
+      json!({}),
+    )
+    .await?
+    .await?;
+
+  let result: RpcResult<Option<Value>> = client
+    .query("create account:two set name = 'account'")
+    .find_one()
+    .await;
+
+  assert!(matches!(
+    result,
+    Err(RpcChannelError::SurrealStatementError {
+      inner: StatementError::UniqueViolation { .. }
+    })
+  ));
+
+  Ok(())
+}
+
+/// This test confirms the client-side syntax checks point at the location of
+/// the errors they find.
+#[test]
+fn it_checks_query_syntax() {
+  let unterminated = check_query("select * from account\nwhere name = 'john", &json!({}));
+
+  assert!(matches!(
+    unterminated,
+    Err(error) if error.kind == SyntaxErrorKind::UnterminatedString
+      && (error.line, error.column) == (2, 14)
+      && error.snippet == "where name = 'john\n             ^"
+  ));
+
+  let unbalanced = check_query(
+    "select * from account where (name = $name];",
+    &json!({ "name": "john" }),
+  );
+
+  assert!(matches!(
+    unbalanced,
+    Err(error) if error.kind == SyntaxErrorKind::UnexpectedDelimiter {
+      found: ']',
+      expected: Some(')')
+    }
+  ));
+
+  let unknown = check_query(
+    "let $a = 1; select * from account where age > $a and name = $name",
+    &json!({}),
+  );
+
+  assert!(matches!(
+    unknown,
+    Err(error) if error.kind == SyntaxErrorKind::UnknownParam { name: "name".to_owned() }
+  ));
+
+  assert!(check_query(
+    "select * from account where name = $name -- or $comment\n; define function fn::a($b: string) { return $b; }",
+    &json!({ "name": "john" })
+  )
+  .is_ok());
+}
+
+/// This test confirms the parse errors reported by the database show the
+/// failing line of the query.
+#[tokio::test]
+async fn it_maps_parse_errors_to_the_query() -> RpcResult<()> {
+  let mut client = open_connection().await?;
+
+  let result = client
+    .query("select * from account;\nselec * from account")
+    .find_many::<Value>()
+    .await;
+
+  let snippet = match result {
+    Err(RpcChannelError::SurrealStatementError {
+      inner: StatementError::Parse { snippet, .. },
+    }) => snippet,
+    _ => None,
+  };
+
+  assert!(snippet.is_some_and(|snippet| snippet.starts_with("selec * from account\n")));
+
+  Ok(())
+}
+
+/// This test confirms the record ids are parsed from and displayed in their
+/// `table:id` form, with the complex ids being escaped.
+#[test]
+fn it_parses_record_ids() {
+  let simple: RecordId = "account:one".parse().expect("Failed to parse a simple id");
+  let complex: RecordId = "account:⟨john doe⟩"
+    .parse()
+    .expect("Failed to parse a complex id");
+
+  assert_eq!(("account", "one"), (simple.table(), simple.id()));
+  assert_eq!("john doe", complex.id());
+  assert_eq!("account:⟨john doe⟩", complex.to_string());
+  assert!("account".parse::<RecordId>().is_err());
+
+  let escaped = RecordId::new("account", "a\\b⟩c");
+  let numeric: RecordId = "account:42".parse().expect("Failed to parse a numeric id");
+  let digits: RecordId = "account:⟨42⟩".parse().expect("Failed to parse a digits id");
+
+  assert_eq!("account:⟨a\\\\b\\⟩c⟩", escaped.to_string());
+  assert_eq!(Ok(escaped), "account:⟨a\\\\b\\⟩c⟩".parse());
+  assert!(numeric.is_numeric() && !digits.is_numeric());
+  assert_eq!(RecordId::numeric("account", 42), numeric);
+  assert_eq!("account:42", numeric.to_string());
+  assert_eq!("account:⟨42⟩", digits.to_string());
+  assert_eq!("account:⟨42⟩", RecordId::new("account", "42").to_string());
+}
+
+/// This test confirms the ids of the graph queries are bound as parameters
+/// rather than written in the queries.
+#[tokio::test]
+async fn it_binds_the_record_ids_of_graph_queries() -> RpcResult<()> {
+  let server = FakeServer::start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  let account = RecordId::new("account", "one⟩; DELETE account; --");
+  let file = RecordId::numeric("file", 1);
+
+  client
+    .relate(&account, "write", &file, json!({ "at": "now" }))
+    .await?;
+  client
+    .outgoing::<Value>(&account, "write", "file", &[])
+    .await?;
+
+  let requests: Vec<(String, Value)> = server
+    .requests()
+    .into_iter()
+    .filter(|request| request.method == "query")
+    .map(|request| {
+      (
+        request.params[0].as_str().unwrap_or_default().to_owned(),
+        request.params[1].clone(),
+      )
+    })
+    .collect();
+
+  assert_eq!(2, requests.len());
+
+  for (query, _) in &requests {
+    assert!(!query.contains("DELETE"), "{query}");
+  }
+
+  assert_eq!(json!("one⟩; DELETE account; --"), requests[0].1["from_id"]);
+  assert_eq!(json!(1), requests[0].1["to_id"]);
+  assert_eq!(json!("one⟩; DELETE account; --"), requests[1].1["id"]);
+
+  Ok(())
+}
+
+/// This test confirms a `LinkVec` deserializes from both the record ids and the
+/// fetched records, and always serializes back into the ids.
+#[test]
+fn it_deserializes_links_with_and_without_fetch() {
+  let unfetched: User = serde_json::from_value(json!({
+    "id": "account:one",
+    "name": "account one",
+    "written_files": ["file:one"]
+  }))
+  .expect("Failed to deserialize the unfetched links");
+
+  let fetched: User = serde_json::from_value(json!({
+    "id": "account:one",
+    "name": "account one",
+    "written_files": [{ "id": "file:one", "name": "file one" }]
+  }))
+  .expect("Failed to deserialize the fetched links");
+
+  let file = RecordId::new("file", "one");
+
+  assert_eq!(vec![&file], unfetched.written_files.ids());
+  assert!(unfetched.written_files.loaded().is_none());
+
+  assert_eq!(vec![&file], fetched.written_files.ids());
+  assert_eq!(
+    Some(vec!["file one"]),
+    fetched
+      .written_files
+      .loaded()
+      .map(|files| files.iter().map(|file| file.name.as_str()).collect())
+  );
+
+  assert_eq!(json!(["file:one"]), fetched.into_json()["written_files"]);
+  assert!(serde_json::from_value::<Link<File>>(json!({ "name": "no id" })).is_err());
+}
+
+/// This test confirms the `Client::relate` function creates typed edges that
+/// can then be traversed in both directions.
+#[tokio::test]
+async fn it_relates_and_traverses_records() -> RpcResult<()> {
+  #[derive(serde::Serialize, serde::Deserialize)]
+  struct Write {
+    words: u32,
+  }
+
+  let mut client = open_connection().await?;
+
+  client
+    .send_query(
+      "
+      create account:one set name = 'account one';
+      create file:one set name = 'file one';
+    "
+      .to_owned(),
+      json!({}),
+    )
+    .await?
+    .await?;
+
+  let account = RecordId::new("account", "one");
+  let file = RecordId::new("file", "one");
+
+  let edge = client
+    .relate(&account, "write", &file, Write { words: 42 })
+    .await?;
+
+  assert_eq!(Some(42), edge.map(|edge| edge.words));
+
+  let files: Vec<File> = client.outgoing(&account, "write", "file", &[]).await?;
+  let authors: Vec<Value> = client.incoming(&file, "write", "account", &[]).await?;
+  let edges: Vec<Write> = client.outgoing_edges(&account, "write").await?;
+
+  assert_eq!(1, files.len());
+  assert_eq!(1, authors.len());
+  assert_eq!(42, edges[0].words);
+
+  Ok(())
+}
+
+/// This test confirms the client negotiates the CBOR protocol through the
+/// websocket subprotocol, sends binary frames and decodes the tagged values of
+/// the responses. The servers that don't negotiate it are spoken to in JSON.
+#[tokio::test]
+async fn it_negotiates_the_cbor_protocol() -> RpcResult<()> {
+  let text = |text: &str| CborValue::Text(text.to_owned());
+  let row = CborValue::Map(vec![
+    (
+      text("id"),
+      CborValue::Tag(
+        8,
+        Box::new(CborValue::Array(vec![text("account"), text("one")])),
+      ),
+    ),
+    (
+      text("created"),
+      CborValue::Tag(0, Box::new(text("2023-01-01T00:00:00Z"))),
+    ),
+    (
+      text("deleted"),
+      CborValue::Tag(6, Box::new(CborValue::Null)),
+    ),
+  ]);
+  let result = CborValue::Array(vec![CborValue::Map(vec![
+    (text("time"), text("1ms")),
+    (text("status"), text("OK")),
+    (text("result"), CborValue::Array(vec![row])),
+  ])]);
+
+  let server = FakeServer::builder()
+    .cbor()
+    .on_query("from account", FakeResponse::Cbor(result))
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::with_protocol(&server.url(), Protocol::Cbor).await?;
+
+  assert_eq!(Protocol::Cbor, client.protocol());
+
+  let account: Option<Value> = client
+    .find_one("select * from account:one".to_owned(), json!({}))
+    .await?;
+
+  assert_eq!(
+    Some(json!({ "id": "account:one", "created": "2023-01-01T00:00:00Z", "deleted": null })),
+    account
+  );
+
+  let json_only_server = FakeServer::start()
+    .await
+    .expect("Failed to start the fake server");
+  let client = SurrealClient::with_protocol(&json_only_server.url(), Protocol::Cbor).await?;
+
+  assert_eq!(Protocol::Json, client.protocol());
+
+  Ok(())
+}
+
+/// This test confirms the compact datetimes and durations of the CBOR protocol
+/// are decoded into the strings SurrealDB sends for them in JSON.
+#[tokio::test]
+async fn it_decodes_the_compact_cbor_datetimes_and_durations() -> RpcResult<()> {
+  let text = |text: &str| CborValue::Text(text.to_owned());
+  let compact = |tag: u64, parts: &[i64]| {
+    CborValue::Tag(
+      tag,
+      Box::new(CborValue::Array(
+        parts
+          .iter()
+          .map(|part| CborValue::Integer((*part).into()))
+          .collect(),
+      )),
+    )
+  };
+  let row = CborValue::Map(vec![
+    (text("created"), compact(12, &[1672531200, 500000000])),
+    (text("leap"), compact(12, &[951782400])),
+    (text("before_epoch"), compact(12, &[-1, 999999999])),
+    (text("timeout"), compact(14, &[5400])),
+    (
+      text("retention"),
+      compact(14, &[31536000 + 8 * 86400 + 1, 1500]),
+    ),
+    (text("delay"), compact(14, &[])),
+  ]);
+  let result = CborValue::Array(vec![CborValue::Map(vec![
+    (text("time"), text("1ms")),
+    (text("status"), text("OK")),
+    (text("result"), CborValue::Array(vec![row])),
+  ])]);
+
+  let server = FakeServer::builder()
+    .cbor()
+    .on_query("from task", FakeResponse::Cbor(result))
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::with_protocol(&server.url(), Protocol::Cbor).await?;
+  let task: Option<Value> = client
+    .find_one("select * from task".to_owned(), json!({}))
+    .await?;
+
+  assert_eq!(
+    Some(json!({
+      "created": "2023-01-01T00:00:00.500Z",
+      "leap": "2000-02-29T00:00:00Z",
+      "before_epoch": "1969-12-31T23:59:59.999999999Z",
+      "timeout": "1h30m",
+      "retention": "1y1w1d1s1µs500ns",
+      "delay": "0ns",
+    })),
+    task
+  );
+
+  Ok(())
+}
+
+/// This test confirms the messages over the threshold are compressed in both
+/// directions once the permessage-deflate extension is negotiated, whatever
+/// their size and protocol, and that the servers that decline it get
+/// uncompressed messages.
+#[tokio::test]
+async fn it_negotiates_the_message_compression() -> RpcResult<()> {
+  let rows: Vec<Value> = (0..3000)
+    .map(|i| json!({ "id": format!("file:{i}"), "content": "lorem ipsum ".repeat(50) }))
+    .collect();
+  let server = FakeServer::builder()
+    .cbor()
+    .deflate(Compression::threshold(512))
+    .on_query("from file", FakeResponse::rows(rows.clone()))
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  for protocol in [Protocol::Json, Protocol::Cbor] {
+    let compression = Compression::threshold(256).level(9);
+    let mut client = SurrealClient::with_compression(&server.url(), protocol, compression).await?;
+
+    assert_eq!(protocol, client.protocol());
+    assert_eq!(
+      Some(0),
+      client.compression_stats().map(|stats| stats.sent_messages)
+    );
+
+    let content = "dolor sit amet ".repeat(100);
+    let files: Vec<Value> = client
+      .query("select * from file where content != $content")
+      .bind(("content", &content))
+      .find_many()
+      .await?;
+
+    assert_eq!(rows, files);
+    assert!(server
+      .requests()
+      .iter()
+      .any(|request| request.params[1]["content"] == content.as_str()));
+
+    // the small requests are sent as they are
+    client
+      .query("select * from account")
+      .find_many::<Value>()
+      .await?;
+
+    let stats = client
+      .compression_stats()
+      .expect("the compression is negotiated");
+
+    assert_eq!(1, stats.sent_messages);
+    assert!(stats.sent_compressed_bytes < stats.sent_bytes);
+    assert_eq!(1, stats.received_messages);
+    assert!(stats.received_bytes > 1 << 20);
+    assert!(stats.received_compressed_bytes < stats.received_bytes / 10);
+  }
+
+  let uncompressed_server = FakeServer::start()
+    .await
+    .expect("Failed to start the fake server");
+  let mut client = SurrealClient::with_compression(
+    &uncompressed_server.url(),
+    Protocol::Json,
+    Compression::default(),
+  )
+  .await?;
+
+  client
+    .query("select * from file where content != $content")
+    .bind(("content", "dolor sit amet ".repeat(100)))
+    .find_many::<Value>()
+    .await?;
+
+  assert_eq!(None, client.compression_stats());
+  assert_eq!(1, uncompressed_server.queries().len());
+
+  Ok(())
+}
+
+/// This test confirms the versions returned by SurrealDB are parsed and
+/// compared following semver.
+#[test]
+fn it_parses_server_versions() {
+  let beta: ServerVersion = "surrealdb-1.0.0-beta.9+20230402.5eafebd"
+    .parse()
+    .expect("Failed to parse a beta version");
+
+  assert_eq!((1, 0, 0), (beta.major, beta.minor, beta.patch));
+  assert_eq!(Some("beta.9"), beta.pre.as_deref());
+  assert!(beta < "1.0.0-beta.10".parse().unwrap());
+  assert!(beta < ServerVersion::new(1, 0, 0));
+  assert!(beta.is_supported());
+
+  assert!(!ServerVersion::new(0, 3, 0).is_supported());
+  assert!(!ServerVersion::new(3, 0, 0).is_supported());
+  assert!("surrealdb".parse::<ServerVersion>().is_err());
+}
+
+/// This test confirms the client asks for the version of the server when
+/// connecting, refuses to connect to the unsupported versions, and connects
+/// to the servers that don't implement the `version` method.
+#[tokio::test]
+async fn it_detects_the_server_version() -> RpcResult<()> {
+  let server = FakeServer::start()
+    .await
+    .expect("Failed to start the fake server");
+  let client = SurrealClient::new(&server.url()).await?;
+
+  assert_eq!(
+    Some(&FAKE_SERVER_VERSION.parse::<ServerVersion>().unwrap()),
+    client.server_version()
+  );
+
+  let outdated_server = FakeServer::builder()
+    .version("surrealdb-0.3.0")
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  assert!(matches!(
+    SurrealClient::new(&outdated_server.url()).await,
+    Err(RpcChannelError::UnsupportedServer { version }) if version == "surrealdb-0.3.0"
+  ));
+
+  let unversioned_server = FakeServer::builder()
+    .rule(|request| {
+      (request.method == "version").then(|| FakeResponse::error(-32601, "Method not found"))
+    })
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+  let mut client = SurrealClient::new(&unversioned_server.url()).await?;
+
+  assert_eq!(None, client.server_version());
+  client
+    .send_query("select * from account".to_owned(), json!({}))
+    .await?
+    .await?;
+
+  Ok(())
+}
+
+/// This test confirms the session variables are defined with the `let` and
+/// `unset` RPC methods, and that they are merged into the params of the
+/// queries and count as bindings.
+#[tokio::test]
+async fn it_manages_session_variables() -> RpcResult<()> {
+  // the query returns its params so the test can inspect them
+  let server = FakeServer::builder()
+    .rule(|request| {
+      request
+        .query()
+        .map(|_| FakeResponse::rows([request.params[1].clone()]))
+    })
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  client.set_syntax_checks(true);
+  client.set_var("tenant", "acme").await?;
+
+  assert_eq!(Some(&json!("acme")), client.session_vars().get("tenant"));
+  assert!(client
+    .query("select * from account where tenant = $tenant")
+    .missing_bindings()
+    .is_empty());
+
+  let params: Option<Value> = client
+    .find_one(
+      "select * from account where tenant = $tenant and name = $name".to_owned(),
+      json!({ "name": "account one" }),
+    )
+    .await?;
+
+  assert_eq!(
+    Some(json!({ "name": "account one", "tenant": "acme" })),
+    params
+  );
+
+  client.unset_var("tenant").await?;
+
+  let methods: Vec<String> = server
+    .requests()
+    .into_iter()
+    .map(|request| request.method)
+    .filter(|method| method != "version")
+    .collect();
+
+  assert!(client.session_vars().is_empty());
+  assert_eq!(vec!["let", "query", "unset"], methods);
+
+  Ok(())
+}
+
+/// This test confirms the scoped handles switch the session to their
+/// namespace and database before their queries, and only when needed. A
+/// session switched by a `USE` statement is switched back.
+#[tokio::test]
+async fn it_scopes_queries_to_a_namespace() -> RpcResult<()> {
+  let server = FakeServer::start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+
+  for tenant in ["one", "one", "two", "one"] {
+    let mut scoped = client.scoped("tenants", tenant);
+    let users: Vec<Value> = scoped.query("select * from user").find_many().await?;
+
+    assert!(users.is_empty());
+  }
+
+  client
+    .send_query("USE NS tenants DB two".to_owned(), json!({}))
+    .await?
+    .await?;
+  assert_eq!(None, client.session_scope());
+
+  client
+    .scoped("tenants", "one")
+    .query("select * from user")
+    .find_many::<Value>()
+    .await?;
+
+  let uses: Vec<Value> = server
+    .requests()
+    .into_iter()
+    .filter(|request| request.method == "use")
+    .map(|request| request.params)
+    .collect();
+
+  assert_eq!(Some(("tenants", "one")), client.session_scope());
+  assert_eq!(
+    vec![
+      json!(["tenants", "one"]),
+      json!(["tenants", "two"]),
+      json!(["tenants", "one"]),
+      json!(["tenants", "one"])
+    ],
+    uses
+  );
+
+  Ok(())
+}
+
+/// This test confirms the database is exported into a SurrealQL dump made of
+/// its definitions and of the statements that create its records, and that
+/// the dumps are imported back in chunked transactions.
+#[tokio::test]
+async fn it_exports_and_imports_dumps() -> Result<(), DumpError> {
+  let info = |result: Value| {
+    FakeResponse::Result(json!([{ "time": "0ns", "status": "OK", "result": result }]))
+  };
+  let server = FakeServer::builder()
+    .rule(move |request| {
+      let query = request.query()?;
+      let params = &request.params[1];
+
+      match query {
+        "INFO FOR DB" => Some(info(json!({
+          "tb": {
+            "account": "DEFINE TABLE account SCHEMALESS",
+            "write": "DEFINE TABLE write SCHEMALESS"
+          }
+        }))),
+        "INFO FOR TABLE account" => Some(info(json!({
+          "fd": { "name": "DEFINE FIELD name ON account TYPE string" }
+        }))),
+        "INFO FOR TABLE write" => Some(info(json!({}))),
+        _ if query.starts_with("SELECT") && params["__page_start"] == 0 => {
+          match params["table"].as_str()? {
+            "account" => Some(FakeResponse::rows([json!({
+              "surql": "{ created: d'2023-01-01T00:00:00Z', id: account:one, name: 'account one', photo: file:one }"
+            })])),
+            _ => Some(FakeResponse::rows([json!({
+              "in": "account:one",
+              "out": "file:one",
+              "surql": "{ id: write:one, in: account:one, out: file:one }"
+            })])),
+          }
+        }
+        _ if query.starts_with("SELECT") => Some(FakeResponse::rows([])),
+        _ => None,
+      }
+    })
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  let mut dump = Vec::new();
+
+  client.export(&mut dump).await?;
+
+  let dump = String::from_utf8(dump).expect("The dump is not valid UTF-8");
+
+  for statement in [
+    "OPTION IMPORT;",
+    "DEFINE TABLE account SCHEMALESS;",
+    "DEFINE FIELD name ON account TYPE string;",
+    "INSERT INTO account [{ created: d'2023-01-01T00:00:00Z', id: account:one, name: 'account one', photo: file:one }];",
+    "RELATE account:one->write->file:one CONTENT { id: write:one, in: account:one, out: file:one };",
+  ] {
+    assert!(
+      dump.contains(statement),
+      "missing `{statement}` in:\n{dump}"
+    );
+  }
+
+  let mut progress = Vec::new();
+
+  client
+    .import_with_progress(dump.as_bytes(), 2, |p| progress.push(p.statements_done))
+    .await?;
+
+  let transactions: Vec<String> = server
+    .queries()
+    .into_iter()
+    .filter(|query| query.starts_with("OPTION IMPORT;\nBEGIN TRANSACTION;\n"))
+    .collect();
+
+  assert_eq!(vec![2, 4, 5], progress);
+  assert_eq!(3, transactions.len());
+  assert!(transactions[0].contains("BEGIN TRANSACTION;\nDEFINE TABLE account SCHEMALESS;"));
+  assert!(transactions[2].ends_with("out: file:one };\nCOMMIT TRANSACTION;"));
+
+  Ok(())
+}
+
+/// This test confirms a dump is imported as it is read: the statements are
+/// sent once their `;` is read, before the rest of the dump is available.
+#[tokio::test]
+async fn it_streams_the_imported_dumps() -> Result<(), DumpError> {
+  let server = FakeServer::start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  let (mut writer, reader) = tokio::io::duplex(64);
+
+  let import = client.import_with_progress(reader, 1, |_| {});
+  let feed = async {
+    writer
+      .write_all(b"OPTION IMPORT;\nCREATE file:one\n  SET name = 'one; -- not a comment';\n")
+      .await?;
+
+    while server.queries().is_empty() {
+      tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    // the last statement doesn't need its `;`
+    writer
+      .write_all(b"-- the last file\nCREATE file:two")
+      .await?;
+    drop(writer);
+
+    Ok::<_, std::io::Error>(())
+  };
+
+  let (imported, fed) = tokio::join!(import, feed);
+  imported?;
+  fed?;
+
+  assert_eq!(
+    vec![
+      "OPTION IMPORT;\nBEGIN TRANSACTION;\nCREATE file:one\n  SET name = 'one; -- not a comment';\nCOMMIT TRANSACTION;",
+      "OPTION IMPORT;\nBEGIN TRANSACTION;\nCREATE file:two;\nCOMMIT TRANSACTION;",
+    ],
+    server.queries()
+  );
+
+  Ok(())
+}
+
+/// This test confirms the fake server answers with the responses of its rules
+/// and script, and that the client surfaces the injected errors, malformed
+/// frames and disconnects instead of hanging.
+#[tokio::test]
+async fn it_handles_the_faults_of_a_fake_server() -> RpcResult<()> {
+  let server = FakeServer::builder()
+    .credentials("root", "root")
+    .on_query(
+      "from account",
+      FakeResponse::rows([json!({ "id": "account:one" })]),
+    )
+    .on_query(
+      "from missing",
+      FakeResponse::error(-32000, "There was a problem"),
+    )
+    .script([
+      FakeResponse::statement_error("Database record `file:one` already exists"),
+      FakeResponse::Malformed("{ not json".to_owned()),
+      FakeResponse::Disconnect.delayed(Duration::from_millis(10)),
+    ])
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+
+  assert!(client.signin("root", "wrong").await.is_err());
+  client.signin("root", "root").await?;
+
+  let account: Option<RecordId> = client
+    .find_one_key("id", "select * from account".to_owned(), json!({}))
+    .await?;
+
+  assert_eq!(Some(RecordId::new("account", "one")), account);
+  assert!(matches!(
+    client
+      .find_one::<Value>("select * from missing".to_owned(), json!({}))
+      .await,
+    Err(RpcChannelError::SurrealQueryError { .. })
+  ));
+  assert!(matches!(
+    client
+      .find_one::<Value>("create file:one".to_owned(), json!({}))
+      .await,
+    Err(RpcChannelError::SurrealStatementError {
+      inner: StatementError::RecordAlreadyExists { .. }
+    })
+  ));
+
+  client.set_retry_policy(RetryPolicy::none().with_timeout(Duration::from_millis(50)));
+
+  assert!(matches!(
+    client
+      .find_one::<Value>("create file:two".to_owned(), json!({}))
+      .await,
+    Err(RpcChannelError::Timeout { .. })
+  ));
+  assert!(client
+    .find_one::<Value>("create file:three".to_owned(), json!({}))
+    .await
+    .is_err());
+
+  // the server keeps accepting connections after a disconnect
+  let mut client = SurrealClient::new(&server.url()).await?;
+  client
+    .send_query("create file:four".to_owned(), json!({}))
+    .await?
+    .await?;
+
+  assert_eq!(6, server.queries().len());
+
+  Ok(())
+}
+
+/// This test confirms only the read-only queries are retried after a
+/// transient failure, and that no query is retried once the connection is
+/// closed.
+#[tokio::test]
+async fn it_retries_the_read_only_queries() -> RpcResult<()> {
+  let conflict = FakeResponse::statement_error(
+    "Failed to commit transaction due to a read or write conflict. This transaction can be retried",
+  );
+  let server = FakeServer::builder()
+    .on_query("conflict", conflict)
+    .on_query("disconnect", FakeResponse::Disconnect)
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  client.set_retry_policy(RetryPolicy {
+    initial_backoff: Duration::ZERO,
+    ..RetryPolicy::exponential(2)
+  });
+
+  let queries = [
+    ("select * from conflict; info for db", 3),
+    ("select * from (create conflict)", 1),
+    ("select fn::conflict() from account", 1),
+    ("select * from conflict; delete account", 1),
+    ("select '; create' from conflict", 3),
+  ];
+
+  for (query, attempts) in queries {
+    let sent = server.queries().len();
+
+    assert!(client
+      .find_one::<Value>(query.to_owned(), json!({}))
+      .await
+      .is_err());
+    assert_eq!(attempts, server.queries().len() - sent, "{query}");
+  }
+
+  let sent = server.queries().len();
+
+  assert!(client
+    .find_one::<Value>("select * from disconnect".to_owned(), json!({}))
+    .await
+    .is_err());
+  assert!(client.is_closed());
+  assert_eq!(1, server.queries().len() - sent);
+
+  Ok(())
+}
+
+/// This test confirms the tasks wait for a client of the [SurrealPool] to be
+/// released, and that a client whose connection was closed is replaced.
+#[tokio::test]
+async fn it_replaces_the_closed_clients_of_the_pool() -> RpcResult<()> {
+  let server = FakeServer::builder()
+    .on_query("disconnect", FakeResponse::Disconnect)
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let url = server.url();
+  let pool = SurrealPool::new(vec![SurrealClient::new(&url).await?]).with_connect(move || {
+    let url = url.clone();
+
+    async move { SurrealClient::new(&url).await }
+  });
+
+  let mut client = pool.get().await?;
+  assert!(tokio::time::timeout(Duration::from_millis(50), pool.get())
+    .await
+    .is_err());
+
+  assert!(client
+    .find_one::<Value>("select * from disconnect".to_owned(), json!({}))
+    .await
+    .is_err());
+  assert!(client.is_closed());
+  drop(client);
+
+  let mut client = pool.get().await?;
+  assert!(!client.is_closed());
+  client
+    .find_many::<Value>("select * from account".to_owned(), json!({}))
+    .await?;
+
+  Ok(())
+}
+
+/// This test confirms the clients of the [SurrealPool] are handed out with the
+/// session they had when they joined the pool, and that the clients whose
+/// session can't be reset are replaced.
+#[tokio::test]
+async fn it_resets_the_session_of_the_pooled_clients() -> RpcResult<()> {
+  let server = FakeServer::start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let url = server.url();
+  let mut scoped = SurrealClient::new(&url).await?;
+  scoped.use_namespace("app", "main").await?;
+  scoped.set_var("region", "eu").await?;
+
+  let pool = SurrealPool::new(vec![scoped]).with_connect(move || {
+    let url = url.clone();
+
+    async move { SurrealClient::new(&url).await }
+  });
+
+  let mut client = pool.get().await?;
+  client.use_namespace("tenant", "one").await?;
+  client.set_var("tenant", "one").await?;
+  client.set_var("region", "us").await?;
+  drop(client);
+
+  let client = pool.get().await?;
+  let vars = client.session_vars().clone();
+
+  assert_eq!(Some(("app", "main")), client.session_scope());
+  assert_eq!(json!({ "region": "eu" }), Value::Object(vars));
+
+  let methods: Vec<(String, Value)> = server
+    .requests()
+    .into_iter()
+    .filter(|request| request.method != "version")
+    .map(|request| (request.method, request.params))
+    .collect();
+
+  assert_eq!(
+    vec![
+      ("use".to_owned(), json!(["app", "main"])),
+      ("let".to_owned(), json!(["region", "eu"])),
+      ("use".to_owned(), json!(["tenant", "one"])),
+      ("let".to_owned(), json!(["tenant", "one"])),
+      ("let".to_owned(), json!(["region", "us"])),
+      ("use".to_owned(), json!(["app", "main"])),
+      ("unset".to_owned(), json!(["region"])),
+      ("unset".to_owned(), json!(["tenant"])),
+      ("let".to_owned(), json!(["region", "eu"])),
+    ],
+    methods
+  );
+  drop(client);
+
+  // a session without a namespace can't leave the one it was switched to
+  let url = server.url();
+  let pool = SurrealPool::new(vec![SurrealClient::new(&url).await?]).with_connect(move || {
+    let url = url.clone();
+
+    async move { SurrealClient::new(&url).await }
+  });
+
+  let mut client = pool.get().await?;
+  client.use_namespace("tenant", "one").await?;
+  drop(client);
+
+  let connections = |server: &FakeServer| {
+    server
+      .requests()
+      .iter()
+      .filter(|request| request.method == "version")
+      .count()
+  };
+  let before = connections(&server);
+  let client = pool.get().await?;
+
+  assert_eq!(None, client.session_scope());
+  assert_eq!(before + 1, connections(&server));
+
+  Ok(())
+}
+
+/// This test confirms a dropped [TestDatabase] removes its namespace in the
+/// background, without blocking the runtime, and that a database that was
+/// cleaned up isn't removed twice.
+#[tokio::test]
+async fn it_removes_the_namespace_of_a_dropped_test_database() -> RpcResult<()> {
+  let server = FakeServer::start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let db = TestDatabase::connect(&server.url(), "root", "root").await?;
+  let removal = format!("REMOVE NAMESPACE {}", db.namespace());
+
+  // the single worker of the runtime serves the fake server during the removal
+  drop(db);
+
+  tokio::time::timeout(Duration::from_secs(5), async {
+    while server.queries().is_empty() {
+      tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+  })
+  .await
+  .expect("the namespace is removed in the background");
+
+  assert_eq!(vec![removal.clone()], server.queries());
+
+  let db = TestDatabase::connect(&server.url(), "root", "root").await?;
+  let cleaned_up = format!("REMOVE NAMESPACE {}", db.namespace());
+
+  db.cleanup().await?;
+  tokio::time::sleep(Duration::from_millis(50)).await;
+
+  assert_eq!(vec![removal, cleaned_up], server.queries());
+
+  Ok(())
+}
+
+/// This test confirms the [TestDatabase] loads the `.json` and `.surql`
+/// fixtures into a namespace of its own, that is removed by the cleanup.
+#[tokio::test]
+async fn it_loads_fixtures_into_a_test_database() -> Result<(), FixtureError> {
+  let mut db = open_connection().await?;
+  let namespace = db.namespace().to_owned();
+
+  db.load_fixture("tests/fixtures/accounts.json").await?;
+  db.load_fixture("tests/fixtures/files.surql").await?;
+
+  let accounts: Vec<Value> = db
+    .find_many("select * from account order by name".to_owned(), json!({}))
+    .await?;
+  let files: Vec<Value> = db
+    .find_many(
+      "select * from file where owner = account:one".to_owned(),
+      json!({}),
+    )
+    .await?;
+
+  assert_eq!(2, accounts.len());
+  assert_eq!(Some("account:one"), accounts[0]["id"].as_str());
+  assert_eq!(1, files.len());
+  assert!(matches!(
+    db.load_fixture("tests/fixtures/accounts.csv").await,
+    Err(FixtureError::UnknownFormat { .. })
+  ));
+
+  let namespaces: Value = db
+    .find_one("INFO FOR KV".to_owned(), json!({}))
+    .await?
+    .unwrap_or_default();
+
+  assert!(
+    namespaces["namespaces"].get(&namespace).is_some()
+      || namespaces["ns"].get(&namespace).is_some()
+  );
+
+  db.cleanup().await?;
+
+  let mut client = open_connection().await?;
+  let namespaces: Value = client
+    .find_one("INFO FOR KV".to_owned(), json!({}))
+    .await?
+    .unwrap_or_default();
+
+  // the key of the namespaces depends on the version of SurrealDB
+  assert!(namespaces["namespaces"].get(&namespace).is_none());
+  assert!(namespaces["ns"].get(&namespace).is_none());
+
+  Ok(())
+}
+
+/// This test confirms the calls of a batch are sent without waiting for the
+/// previous responses, and that the responses are returned in the order of
+/// the calls even when one of them failed.
+#[tokio::test]
+async fn it_pipelines_batches() -> RpcResult<()> {
+  let latency = Duration::from_millis(200);
+  let server = FakeServer::builder()
+    .latency(latency)
+    .rule(|request| match request.params[1]["table"].as_str()? {
+      "account" => Some(FakeResponse::rows([json!({ "id": "account:one" })])),
+      "file" => Some(FakeResponse::rows([
+        json!({ "id": "file:new", "name": "new" }),
+      ])),
+      _ => None,
+    })
+    .on_query(
+      "from missing",
+      FakeResponse::statement_error("The table does not exist"),
+    )
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  let batch = client
+    .batch()
+    .select("account")
+    .query("select * from missing", json!({}))
+    .create("file:new", json!({ "name": "new" }))?;
+
+  assert_eq!(3, batch.len());
+
+  let start = std::time::Instant::now();
+  let mut responses = batch.send().await?;
+
+  // sent one after the other the calls would take three times the latency
+  assert!(start.elapsed() < latency * 2);
+
+  #[derive(Deserialize)]
+  struct Account {
+    id: RecordId,
+  }
+
+  let (accounts, missing, file): (Vec<Account>, RpcResult<Vec<Value>>, Option<Value>) = (
+    responses.take(0)?,
+    responses.take(1),
+    responses.take_one(2)?,
+  );
+
+  assert_eq!(RecordId::new("account", "one"), accounts[0].id);
+  assert!(matches!(
+    missing,
+    Err(RpcChannelError::SurrealStatementError { .. })
+  ));
+  assert_eq!(
+    Some("new"),
+    file.as_ref().and_then(|file| file["name"].as_str())
+  );
+  assert!(responses.into_responses().is_empty());
+
+  let queries = server.queries();
+  assert!(queries.contains(&"CREATE type::thing($table, $id) CONTENT $data".to_owned()));
+
+  Ok(())
+}
+
+/// This test confirms taking a response of a batch panics with a message that
+/// tells a call that doesn't exist from a response that was already taken.
+#[tokio::test]
+async fn it_explains_the_responses_of_a_batch_that_cant_be_taken() -> RpcResult<()> {
+  let server = FakeServer::start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  let mut responses = client
+    .batch()
+    .query("select * from account", json!({}))
+    .send()
+    .await?;
+
+  responses.take_response(0)?;
+
+  let mut panic_message = |n: usize| {
+    let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+      let _ = responses.take_response(n);
+    }))
+    .expect_err("taking the response should panic");
+
+    panic
+      .downcast::<String>()
+      .map(|message| *message)
+      .unwrap_or_default()
+  };
+
+  assert_eq!(
+    "the response to the call 0 of the batch was already taken",
+    panic_message(0)
+  );
+  assert_eq!("the batch has no call 1, its length is 1", panic_message(1));
+
+  Ok(())
+}
+
+/// This test confirms the requests over the in-flight limit are rejected or
+/// wait for a slot depending on the [FlowControl], and that the metrics report
+/// the requests awaiting their response.
+#[tokio::test]
+async fn it_limits_the_requests_in_flight() -> RpcResult<()> {
+  let latency = Duration::from_millis(100);
+  let server = FakeServer::builder()
+    .latency(latency)
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  client.set_flow_control(FlowControl::reject(2));
+
+  let first = client
+    .send_query("select * from account".to_owned(), json!({}))
+    .await?;
+  let second = client
+    .send_query("select * from file".to_owned(), json!({}))
+    .await?;
+
+  assert!(matches!(
+    client
+      .send_query("select * from user".to_owned(), json!({}))
+      .await,
+    Err(RpcChannelError::Overloaded { max_in_flight: 2 })
+  ));
+
+  tokio::time::sleep(latency / 2).await;
+
+  let metrics = client.metrics();
+  assert_eq!(2, metrics.in_flight);
+  assert_eq!(0, metrics.queued);
+  assert_eq!(Some(2), metrics.max_in_flight);
+
+  // the requests in flight count against the lowered limit
+  client.set_flow_control(FlowControl::reject(1));
+  first.await?;
+
+  assert!(matches!(
+    client
+      .send_query("select * from user".to_owned(), json!({}))
+      .await,
+    Err(RpcChannelError::Overloaded { max_in_flight: 1 })
+  ));
+
+  second.await?;
+  client
+    .send_query("select * from user".to_owned(), json!({}))
+    .await?
+    .await?;
+
+  // the calls of the batch wait for the previous ones to be answered
+  client.set_flow_control(FlowControl::wait(1));
+
+  let start = std::time::Instant::now();
+  let responses = client
+    .batch()
+    .select("account")
+    .select("file")
+    .select("user")
+    .send()
+    .await?;
+
+  assert!(start.elapsed() >= latency * 3);
+  assert!(responses.into_responses().iter().all(Result::is_ok));
+  assert_eq!(0, client.metrics().in_flight);
+
+  Ok(())
+}
+
+/// This test confirms the requests get increasing ids, and that the responses
+/// whose id matches no request are counted rather than silently dropped.
+#[tokio::test]
+async fn it_counts_unmatched_responses() -> RpcResult<()> {
+  let server = FakeServer::builder()
+    .on_query(
+      "from orphan",
+      FakeResponse::Malformed(json!({ "id": "unknown", "result": null }).to_string()),
+    )
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  let _orphan = client
+    .send_query("select * from orphan".to_owned(), json!({}))
+    .await?;
+
+  client
+    .send_query("select * from account".to_owned(), json!({}))
+    .await?
+    .await?;
+  tokio::time::sleep(Duration::from_millis(50)).await;
+
+  let metrics = client.metrics();
+  assert_eq!(1, metrics.unmatched_responses);
+  assert_eq!(1, metrics.in_flight);
+
+  let ids: Vec<u64> = server
+    .requests()
+    .iter()
+    .map(|request| request.id.parse().expect("The request id is not a number"))
+    .collect();
+
+  assert_eq!(3, ids.len());
+  assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+  Ok(())
+}
+
+/// This test is a regression test for the responses that arrive before their
+/// request is registered: the fake server answers every query as soon as it is
+/// read, and none of the many pipelined queries may be left without response.
+#[tokio::test]
+async fn it_receives_the_instant_responses() -> RpcResult<()> {
+  let server = FakeServer::builder()
+    .rule(|request| {
+      request
+        .query()
+        .map(|query| FakeResponse::rows([json!(query)]))
+    })
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  let mut batch = client.batch();
+
+  for n in 0..200 {
+    batch = batch.query(format!("return {n}"), json!({}));
+  }
+
+  let mut responses = batch.send().await?;
+
+  for n in 0..200 {
+    assert_eq!(vec![format!("return {n}")], responses.take::<String>(n)?);
+  }
+
+  Ok(())
+}
+
+/// This test confirms the requests that were in flight when the connection
+/// was closed, and the requests sent afterwards, fail with a
+/// [`RpcChannelError::ClientClosed`] rather than hang or panic.
+#[tokio::test]
+async fn it_fails_once_the_client_is_closed() -> RpcResult<()> {
+  let server = FakeServer::builder()
+    .script([FakeResponse::Ignore, FakeResponse::Disconnect])
+    .start()
+    .await
+    .expect("Failed to start the fake server");
+
+  let mut client = SurrealClient::new(&server.url()).await?;
+  let in_flight = client
+    .send_query("select * from account".to_owned(), json!({}))
+    .await?;
+
+  assert!(matches!(
+    client
+      .send_query("select * from file".to_owned(), json!({}))
+      .await?
+      .await,
+    Err(RpcChannelError::ClientClosed)
+  ));
+  assert!(matches!(
+    in_flight.await,
+    Err(RpcChannelError::ClientClosed)
+  ));
+  assert!(client.is_closed());
+
+  assert!(matches!(
+    client
+      .send_query("select * from account".to_owned(), json!({}))
+      .await,
+    Err(RpcChannelError::ClientClosed)
+  ));
+  assert_eq!(503, RpcChannelError::ClientClosed.http_status_code());
+
+  Ok(())
+}